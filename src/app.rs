@@ -1,29 +1,191 @@
 use crossterm::event::{KeyCode, KeyEvent};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::Sender;
-use crate::ffmpeg::{self, FfmpegEvent};
+use std::sync::Arc;
+use crate::browser::FileBrowser;
+use crate::chunked;
+use crate::codecs;
+use crate::config::{self, Config};
+use crate::diagnostics::{self, Diagnosis};
+use crate::extra_args;
+use crate::ffmpeg::{self, EncodeSettings, FfmpegEvent};
+use crate::hwaccel;
+use crate::hwcaps::{self, Capabilities};
+use crate::inputs::{self, InputOptions};
+use crate::logs::{self, LogFile};
+use crate::logview::{self, LogViewer};
+use crate::metrics;
+use crate::output::{self, TemplateVars};
+use crate::probe;
+use crate::paths;
+use crate::presets::{self, Preset};
+use crate::preview::{self, JobCommand};
+use crate::queue::{JobStatus, QueueItem};
+use crate::retry::{self, Attempt};
+use crate::search;
+use crate::verify;
 
 pub enum Focus {
+    Preset,
     Encoder,
+    HwDecode,
     Container,
     Qp,
+    TargetVmaf,
     Fps,
     AudioBitrate,
+    PixFmt,
+    Profile,
+    InputArgs,
+    OutputArgs,
+    MaxJobs,
+    Threads,
+    HwDevice,
+    ChunkMode,
+    Verify,
+    OutputTemplate,
+    Overwrite,
+    Metrics,
+    Retries,
+    Fallback,
     Submit,
 }
 
+impl Focus {
+    // Rows stored with each queue entry; the others are machine-wide and apply to the whole run
+    pub fn is_per_entry(&self) -> bool {
+        matches!(
+            self,
+            Focus::Preset
+                | Focus::Encoder
+                | Focus::HwDecode
+                | Focus::Container
+                | Focus::Qp
+                | Focus::Fps
+                | Focus::AudioBitrate
+                | Focus::PixFmt
+                | Focus::Profile
+                | Focus::InputArgs
+                | Focus::OutputArgs
+        )
+    }
+}
+
+// What the text typed into a `TextPrompt` is for
+#[derive(Clone, Copy)]
+pub enum PromptAction {
+    NewPreset,
+    RenamePreset(usize), // index into `App::presets`
+    InputArgs,
+    OutputArgs,
+}
+
+// One-line text input shown over the dashboard
+pub struct TextPrompt {
+    pub title: String,
+    pub value: String,
+    pub action: PromptAction,
+}
+
+// An entry waiting for the user to decide what to do with its existing output
+pub struct OverwritePrompt {
+    pub id: usize,
+    pub output: std::path::PathBuf,
+}
+
+// Where an encode writes
+pub struct JobFiles {
+    pub output: std::path::PathBuf,  // final path
+    pub partial: std::path::PathBuf, // what ffmpeg writes until the job succeeds
+    pub replaces: bool,              // the output existed when the job started and may be replaced
+    pub _lock: Option<output::PartialLock>, // held for the job's lifetime; dropping it removes the lock file
+}
+
+// A queue entry that currently has an ffmpeg process running
+pub struct ActiveJob {
+    pub id: usize, // `QueueItem::id`
+    pub cancel: Arc<AtomicBool>,
+    pub files: Option<JobFiles>, // None for a target-quality search, which writes no output
+    pub progress: f64,
+    pub fps: String,
+    pub speed: String,
+    pub bitrate: String,
+    pub time: String,
+}
+
+impl ActiveJob {
+    // A target-quality search; it has no output yet
+    pub fn searching(id: usize) -> Self {
+        Self::with_files(id, None)
+    }
+
+    pub fn new(id: usize, output: std::path::PathBuf) -> Self {
+        let files = JobFiles {
+            partial: output::partial_path(&output),
+            replaces: output.exists(),
+            _lock: output::PartialLock::acquire(&output),
+            output,
+        };
+        Self::with_files(id, Some(files))
+    }
+
+    fn with_files(id: usize, files: Option<JobFiles>) -> Self {
+        Self {
+            id,
+            cancel: Arc::new(AtomicBool::new(false)),
+            files,
+            progress: 0.0,
+            fps: String::from("0"),
+            speed: String::from("0x"),
+            bitrate: String::from("0kbits/s"),
+            time: String::from("00:00:00"),
+        }
+    }
+
+    // Deletes what a failed or cancelled encode wrote so far
+    pub fn discard_partial(&self) {
+        if let Some(files) = &self.files {
+            let _ = std::fs::remove_file(&files.partial);
+        }
+    }
+}
+
 pub struct App {
     pub should_quit: bool,
     pub focus: Focus,
     
     // Data
     // File Queue
-    pub queue: Vec<(String, String)>, // (input, output)
-    pub current_file_index: usize,
-    pub completed_files: Vec<String>,
+    pub queue: Vec<QueueItem>,
+    pub next_item_id: usize,
+    // Entry whose settings the grid edits; None edits the defaults for newly added files
+    pub selected_queue_index: Option<usize>,
+    pub default_settings: EncodeSettings,
+    pub input_options: InputOptions,
+
+    // Output naming
+    pub output_template_list: Vec<String>,
+    pub selected_output_template_index: usize,
+    pub output_dir: Option<std::path::PathBuf>, // overrides the input's directory for {dir}
+    pub overwrite_policy_list: Vec<String>,
+    pub selected_overwrite_policy_index: usize,
+    pub overwrite_prompt: Option<OverwritePrompt>,
+    pub cleaned_dirs: std::collections::HashSet<std::path::PathBuf>, // output folders already cleared of stale temp files
+    pub resolution_probe: Option<std::sync::mpsc::Receiver<(usize, Option<String>)>>, // (entry id, resolution) while probing
 
     // Configuration
+    pub presets: Vec<Preset>, // built-in presets first, then the user's
+    pub builtin_preset_count: usize,
+    pub selected_preset_index: Option<usize>, // None while the grid holds custom values
+    pub text_prompt: Option<TextPrompt>,
     pub encoders: Vec<String>,
     pub selected_encoder_index: usize,
+    pub unavailable_encoders: Vec<(String, String)>, // (encoder, reason), skipped when cycling
+    pub hwaccels: Vec<String>,
+    pub vmaf_available: bool, // from encoder detection; false until it finishes
+    pub encoder_detection: Option<std::sync::mpsc::Receiver<Result<Capabilities, String>>>,
+    pub hw_decode_list: Vec<String>,
+    pub selected_hw_decode_index: usize,
     pub container_list: Vec<String>,
     pub selected_container_index: usize,
     pub qp_list: Vec<String>,
@@ -32,139 +194,1928 @@ pub struct App {
     pub selected_fps_index: usize,
     pub audio_bitrate_list: Vec<String>,
     pub selected_audio_bitrate_index: usize,
+    pub pix_fmt_list: Vec<String>,
+    pub selected_pix_fmt_index: usize,
+    pub profile_list: Vec<String>, // depends on the selected encoder
+    pub selected_profile_index: usize,
+    pub input_args: String, // extra ffmpeg options, as typed
+    pub output_args: String,
+
+    // Worker pool
+    pub max_jobs_list: Vec<String>,
+    pub selected_max_jobs_index: usize,
+    pub threads_list: Vec<String>,
+    pub selected_threads_index: usize,
+    pub hw_device_list: Vec<String>, // "Auto" and the render nodes found at startup
+    pub selected_hw_device_index: usize,
+    pub chunk_mode_list: Vec<String>,
+    pub selected_chunk_mode_index: usize,
+    pub verify_list: Vec<String>,
+    pub selected_verify_index: usize,
+    pub metrics_list: Vec<String>,
+    pub selected_metrics_index: usize,
+    pub retries_list: Vec<String>,
+    pub selected_retries_index: usize,
+    pub fallback_list: Vec<String>,
+    pub selected_fallback_index: usize,
+    pub target_vmaf_list: Vec<String>,
+    pub selected_target_vmaf_index: usize,
+
+    // File picker overlay, when open
+    pub browser: Option<FileBrowser>,
+
+    // Full-screen log viewer, when open
+    pub log_viewer: Option<LogViewer>,
+
+    // UI options from the config file
+    pub log_lines: usize,
+    pub show_commands: bool, // command preview instead of the log
+    pub command_preview: Option<Result<Vec<JobCommand>, String>>, // built when the selection or settings change, not per frame
+    pub last_diagnosed: Option<usize>, // id of the entry that failed most recently with a known cause
+    pub browser_show_all: bool,
+
+    // Encoding state
+    pub is_encoding: bool,
+    pub active_jobs: Vec<ActiveJob>,
+    pub log_messages: Vec<String>,
+    pub log_dir: Option<std::path::PathBuf>, // where job logs are written; None when turned off
+}
+
+impl App {
+    pub fn new(args: Vec<String>) -> Self {
+        let qp_list: Vec<String> = (0..=53).map(|i| i.to_string()).collect();
+        let audio_bitrate_list = vec![
+            "128k".to_string(),
+            "160k".to_string(),
+            "192k".to_string(),
+            "256k".to_string(),
+            "320k".to_string(),
+        ];
+        let container_list = vec![
+            "mp4".to_string(),
+            "mkv".to_string(),
+            "avi".to_string(),
+            "webm".to_string(),
+            "gif".to_string(),
+            "mov".to_string(),
+        ];
+        let fps_list = vec![
+            "Same".to_string(),
+            "24".to_string(),
+            "30".to_string(),
+            "60".to_string(),
+            "120".to_string(),
+            "144".to_string(),
+        ];
+
+        let max_jobs_list = vec![
+            "1".to_string(),
+            "2".to_string(),
+            "3".to_string(),
+            "4".to_string(),
+            "6".to_string(),
+            "8".to_string(),
+            "12".to_string(),
+            "16".to_string(),
+        ];
+        let threads_list = vec![
+            "Auto".to_string(),
+            "1".to_string(),
+            "2".to_string(),
+            "4".to_string(),
+            "8".to_string(),
+            "16".to_string(),
+        ];
+
+        let mut app = Self {
+            should_quit: false,
+            focus: Focus::Encoder,
+            
+            queue: Vec::new(),
+            next_item_id: 0,
+            selected_queue_index: None,
+            input_options: InputOptions::default(),
+            output_template_list: output::template_choices(None),
+            selected_output_template_index: 0, // "{dir}/{stem}_encoded.{container}"
+            output_dir: None,
+            overwrite_policy_list: output::overwrite_policy_choices(),
+            selected_overwrite_policy_index: 0, // Ask
+            overwrite_prompt: None,
+            cleaned_dirs: std::collections::HashSet::new(),
+            resolution_probe: None,
+            default_settings: EncodeSettings {
+                encoder: String::new(),
+                container: String::new(),
+                qp: String::new(),
+                fps: String::new(),
+                audio_bitrate: String::new(),
+                pix_fmt: String::new(),
+                profile: String::new(),
+                hw_decode: String::new(),
+                input_args: String::new(),
+                output_args: String::new(),
+                preset: None,
+                hw_device: None,
+            },
+
+            presets: presets::built_in(),
+            builtin_preset_count: presets::built_in().len(),
+            selected_preset_index: None,
+            text_prompt: None,
+
+            encoders: vec![
+                "libx264".to_string(),
+                "libx265".to_string(),
+                "libvpx-vp9".to_string(),
+                "libaom-av1".to_string(),
+                "libsvtav1".to_string(),
+                "librav1e".to_string(),
+                "prores_ks".to_string(),
+                "dnxhd".to_string(),
+                "ffv1".to_string(),
+                "libx264rgb".to_string(),
+                "h264_nvenc".to_string(),
+                "hevc_nvenc".to_string(),
+                "av1_nvenc".to_string(),
+                "h264_vaapi".to_string(),
+                "hevc_vaapi".to_string(),
+                "av1_vaapi".to_string(),
+                "h264_qsv".to_string(),
+                "hevc_qsv".to_string(),
+                "av1_qsv".to_string(),
+            ],
+            selected_encoder_index: 0,
+            unavailable_encoders: Vec::new(),
+            hwaccels: Vec::new(),
+            vmaf_available: false,
+            encoder_detection: None,
+            hw_decode_list: hwaccel::decode_choices(),
+            selected_hw_decode_index: 0, // Software decoding
+            container_list,
+            selected_container_index: 0,
+            qp_list,
+            selected_qp_index: 23, // Default to something reasonable like 23
+            fps_list,
+            selected_fps_index: 0, // Default "Same"
+            audio_bitrate_list,
+            selected_audio_bitrate_index: 0,
+            pix_fmt_list: codecs::pix_fmt_choices(),
+            selected_pix_fmt_index: 0, // Default "Auto" (yuv420p)
+            profile_list: vec![codecs::PROFILE_DEFAULT.to_string()],
+            selected_profile_index: 0,
+            input_args: String::new(),
+            output_args: String::new(),
+            max_jobs_list,
+            selected_max_jobs_index: 0, // One file at a time
+            threads_list,
+            selected_threads_index: 0, // Let ffmpeg decide
+            hw_device_list: std::iter::once(hwaccel::DEVICE_AUTO.to_string()).chain(hwaccel::render_devices()).collect(),
+            selected_hw_device_index: 0, // The driver's default device
+            chunk_mode_list: chunked::chunk_mode_choices(),
+            selected_chunk_mode_index: 0, // Whole-file encoding
+            verify_list: verify::verify_mode_choices(),
+            selected_verify_index: 0, // No verification
+            metrics_list: metrics::metrics_mode_choices(),
+            selected_metrics_index: 0, // No quality measurement
+            retries_list: retry::retry_choices(),
+            selected_retries_index: 0, // Fail on the first error
+            fallback_list: retry::fallback_choices(),
+            selected_fallback_index: 0, // Keep the settings as queued
+            target_vmaf_list: search::target_choices(),
+            selected_target_vmaf_index: 0, // Use the QP as given
+            
+            browser: None,
+
+            log_viewer: None,
+
+            log_lines: 10,
+            show_commands: false,
+            command_preview: None,
+            last_diagnosed: None,
+            browser_show_all: false,
+
+            is_encoding: false,
+            active_jobs: Vec::new(),
+            log_messages: Vec::new(),
+            log_dir: logs::default_dir(),
+        };
+        match config::load() {
+            Ok(config) => {
+                for error in app.apply_config(config) {
+                    app.log_messages.push(format!("CONFIG ERROR: {}", error));
+                }
+            }
+            Err(e) => app.log_messages.push(format!("CONFIG ERROR: {}", e)),
+        }
+        match presets::load_user_presets() {
+            Ok(list) => {
+                for preset in list {
+                    match presets::validate_name(&preset.name, &app.presets) {
+                        Ok(()) => app.presets.push(preset),
+                        Err(e) => app.log_messages.push(format!("CONFIG ERROR: presets: {}", e)),
+                    }
+                }
+            }
+            Err(e) => app.log_messages.push(format!("CONFIG ERROR: {}", e)),
+        }
+        app.default_settings = app.current_settings();
+        app.encoder_detection = Some(hwcaps::start_detection(app.encoders.clone(), app.hw_device()));
+
+        match inputs::parse_args(args) {
+            Ok(cli) => {
+                app.input_options = cli.input;
+                app.output_dir = cli.output_dir;
+                // A template from the command line wins over the configured one
+                if let Some(template) = cli.template {
+                    app.output_template_list.retain(|t| *t != template);
+                    app.output_template_list.insert(0, template);
+                    app.selected_output_template_index = 0;
+                }
+                for path in cli.paths {
+                    app.add_path(&path);
+                }
+            }
+            Err(e) => app.log_messages.push(format!("ERROR: {}", e)),
+        }
+        app
+    }
+
+    // Applies the user config over the built-in defaults. Problems are returned so
+    // they can be reported instead of stopping the program.
+    fn apply_config(&mut self, config: Config) -> Vec<String> {
+        let mut errors = Vec::new();
+        ffmpeg::set_tool_paths(config.ffmpeg_path, config.ffprobe_path);
+
+        match config.encoders {
+            Some(list) if list.is_empty() => errors.push("encoders: list is empty".to_string()),
+            Some(list) => self.encoders = list,
+            None => {}
+        }
+        match config.containers {
+            Some(list) if list.is_empty() => errors.push("containers: list is empty".to_string()),
+            Some(list) => self.container_list = list,
+            None => {}
+        }
+        if let Some(template) = config.output_template {
+            match output::validate_template(&template) {
+                Ok(()) => self.output_template_list = output::template_choices(Some(&template)),
+                Err(e) => errors.push(format!("output_template: {}", e)),
+            }
+        }
+
+        match config.log_dir {
+            Some(dir) if dir.trim().is_empty() => self.log_dir = None,
+            Some(dir) => self.log_dir = Some(std::path::PathBuf::from(dir)),
+            None => {}
+        }
+
+        match config.ui.log_lines {
+            Some(0) => errors.push("ui.log_lines: must be at least 1".to_string()),
+            Some(lines) => self.log_lines = lines,
+            None => {}
+        }
+        if let Some(show_all) = config.ui.show_all_files {
+            self.browser_show_all = show_all;
+        }
+
+        let d = config.defaults;
+        select_default("defaults.encoder", d.encoder, &self.encoders, &mut self.selected_encoder_index, &mut errors);
+        select_default("defaults.hw_decode", d.hw_decode, &self.hw_decode_list, &mut self.selected_hw_decode_index, &mut errors);
+        select_default("defaults.container", d.container, &self.container_list, &mut self.selected_container_index, &mut errors);
+        select_default("defaults.qp", d.qp, &self.qp_list, &mut self.selected_qp_index, &mut errors);
+        select_default("defaults.fps", d.fps, &self.fps_list, &mut self.selected_fps_index, &mut errors);
+        select_default("defaults.audio_bitrate", d.audio_bitrate, &self.audio_bitrate_list, &mut self.selected_audio_bitrate_index, &mut errors);
+        select_default("defaults.pix_fmt", d.pix_fmt, &self.pix_fmt_list, &mut self.selected_pix_fmt_index, &mut errors);
+        self.refresh_profiles();
+        select_default("defaults.profile", d.profile, &self.profile_list, &mut self.selected_profile_index, &mut errors);
+        for (name, value, field) in [
+            ("defaults.input_args", d.input_args, &mut self.input_args),
+            ("defaults.output_args", d.output_args, &mut self.output_args),
+        ] {
+            let Some(value) = value else { continue };
+            match extra_args::parse(&value) {
+                Ok(_) => *field = value,
+                Err(e) => errors.push(format!("{}: {}", name, e)),
+            }
+        }
+        select_default("defaults.max_jobs", d.max_jobs, &self.max_jobs_list, &mut self.selected_max_jobs_index, &mut errors);
+        select_default("defaults.threads", d.threads, &self.threads_list, &mut self.selected_threads_index, &mut errors);
+        select_default("defaults.hw_device", d.hw_device, &self.hw_device_list, &mut self.selected_hw_device_index, &mut errors);
+        select_default("defaults.chunk_mode", d.chunk_mode, &self.chunk_mode_list, &mut self.selected_chunk_mode_index, &mut errors);
+        select_default("defaults.verify", d.verify, &self.verify_list, &mut self.selected_verify_index, &mut errors);
+        select_default("defaults.metrics", d.metrics, &self.metrics_list, &mut self.selected_metrics_index, &mut errors);
+        select_default("defaults.retries", d.retries, &self.retries_list, &mut self.selected_retries_index, &mut errors);
+        select_default("defaults.fallback", d.fallback, &self.fallback_list, &mut self.selected_fallback_index, &mut errors);
+        select_default("defaults.target_vmaf", d.target_vmaf, &self.target_vmaf_list, &mut self.selected_target_vmaf_index, &mut errors);
+        select_default("defaults.overwrite", d.overwrite, &self.overwrite_policy_list, &mut self.selected_overwrite_policy_index, &mut errors);
+        errors
+    }
+
+    // Writes what the grid shows now as the defaults for future runs, keeping the
+    // rest of the config file as it is
+    pub fn save_defaults(&mut self) {
+        let mut config = match config::load() {
+            Ok(config) => config,
+            Err(e) => {
+                self.log_messages.push(format!("Not saving, fix the config first: {}", e));
+                return;
+            }
+        };
+        config.defaults = config::Defaults {
+            encoder: Some(self.encoders[self.selected_encoder_index].clone()),
+            hw_decode: Some(self.hw_decode_list[self.selected_hw_decode_index].clone()),
+            container: Some(self.container_list[self.selected_container_index].clone()),
+            qp: Some(self.qp_list[self.selected_qp_index].clone()),
+            fps: Some(self.fps_list[self.selected_fps_index].clone()),
+            audio_bitrate: Some(self.audio_bitrate_list[self.selected_audio_bitrate_index].clone()),
+            pix_fmt: Some(self.pix_fmt_list[self.selected_pix_fmt_index].clone()),
+            profile: Some(self.profile_list[self.selected_profile_index].clone()),
+            input_args: Some(self.input_args.clone()),
+            output_args: Some(self.output_args.clone()),
+            max_jobs: Some(self.max_jobs_list[self.selected_max_jobs_index].clone()),
+            threads: Some(self.threads_list[self.selected_threads_index].clone()),
+            hw_device: Some(self.hw_device_list[self.selected_hw_device_index].clone()),
+            chunk_mode: Some(self.chunk_mode().to_string()),
+            verify: Some(self.verify_mode().to_string()),
+            metrics: Some(self.metrics_mode().to_string()),
+            retries: Some(self.retries_list[self.selected_retries_index].clone()),
+            fallback: Some(self.fallback_list[self.selected_fallback_index].clone()),
+            target_vmaf: Some(self.target_vmaf_list[self.selected_target_vmaf_index].clone()),
+            overwrite: Some(self.overwrite_policy().to_string()),
+        };
+        config.output_template = Some(self.output_template().to_string());
+
+        match config::save(&config) {
+            Ok(path) => self.log_messages.push(format!("Saved current settings as defaults in {}", path.display())),
+            Err(e) => self.log_messages.push(format!("ERROR: {}", e)),
+        }
+    }
+
+    pub fn encoder_problem(&self, encoder: &str) -> Option<&str> {
+        self.unavailable_encoders.iter().find(|(name, _)| name == encoder).map(|(_, reason)| reason.as_str())
+    }
+
+    // Picks up the startup encoder check once it has finished
+    pub fn poll_encoder_detection(&mut self) {
+        let Some(rx) = &self.encoder_detection else { return };
+        let Ok(result) = rx.try_recv() else { return };
+        self.encoder_detection = None;
+        let capabilities = match result {
+            Ok(capabilities) => capabilities,
+            Err(e) => {
+                self.log_messages.push(format!("ERROR: Encoder check failed: {}", e));
+                return;
+            }
+        };
+        for (encoder, reason) in &capabilities.unavailable {
+            self.log_messages.push(format!("Encoder {} unavailable: {}", encoder, reason));
+        }
+        self.hwaccels = capabilities.hwaccels;
+        self.unavailable_encoders = capabilities.unavailable;
+        self.vmaf_available = capabilities.vmaf;
+        self.refresh_command_preview();
+
+        // Don't leave new files defaulting to an encoder that can't run
+        if self.encoder_problem(&self.default_settings.encoder).is_some()
+            && let Some(fallback) = self.encoders.iter().find(|e| self.encoder_problem(e).is_none()).cloned()
+        {
+            self.log_messages.push(format!("Default encoder {} is unavailable, using {}", self.default_settings.encoder, fallback));
+            self.default_settings.encoder = fallback;
+            self.default_settings.preset = None;
+            if self.selected_queue_index.is_none() {
+                let defaults = self.default_settings.clone();
+                self.load_settings(&defaults);
+            }
+        }
+    }
+
+    pub fn selected_preset(&self) -> Option<&Preset> {
+        self.selected_preset_index.map(|i| &self.presets[i])
+    }
+
+    // A preset value the grid doesn't offer, e.g. an encoder left out of the config's list
+    fn preset_error(&self, preset: &Preset) -> Option<String> {
+        let checks = [
+            ("encoder", &preset.encoder, &self.encoders),
+            ("hw_decode", &preset.hw_decode, &self.hw_decode_list),
+            ("container", &preset.container, &self.container_list),
+            ("qp", &preset.qp, &self.qp_list),
+            ("fps", &preset.fps, &self.fps_list),
+            ("audio_bitrate", &preset.audio_bitrate, &self.audio_bitrate_list),
+            ("pix_fmt", &preset.pix_fmt, &self.pix_fmt_list),
+        ];
+        if let Some((key, value, _)) = checks.iter().find(|(_, value, list)| !list.contains(value)) {
+            return Some(format!("preset '{}': {} '{}' is not available", preset.name, key, value));
+        }
+        if !codecs::profile_choices(&preset.encoder).contains(&preset.profile.as_str()) {
+            return Some(format!("preset '{}': {} has no profile '{}'", preset.name, preset.encoder, preset.profile));
+        }
+        if let Some(reason) = self.encoder_problem(&preset.encoder) {
+            return Some(format!("preset '{}': {} is unavailable ({})", preset.name, preset.encoder, reason));
+        }
+        extra_args::for_settings(&preset.settings()).err().map(|e| format!("preset '{}': {}", preset.name, e))
+    }
+
+    fn save_presets(&mut self) {
+        if let Err(e) = presets::save_user_presets(&self.presets[self.builtin_preset_count..]) {
+            self.log_messages.push(format!("ERROR: {}", e));
+        }
+    }
+
+    pub fn new_preset(&mut self) {
+        self.text_prompt = Some(TextPrompt {
+            title: "New preset from current settings".to_string(),
+            value: String::new(),
+            action: PromptAction::NewPreset,
+        });
+    }
+
+    pub fn rename_preset(&mut self) {
+        match self.selected_preset_index {
+            Some(i) if i >= self.builtin_preset_count => {
+                self.text_prompt = Some(TextPrompt {
+                    title: "Rename preset".to_string(),
+                    value: self.presets[i].name.clone(),
+                    action: PromptAction::RenamePreset(i),
+                });
+            }
+            Some(_) => self.log_messages.push("Built-in presets can't be renamed; press n to save a copy".to_string()),
+            None => self.log_messages.push("Select a preset to rename first".to_string()),
+        }
+    }
+
+    pub fn delete_preset(&mut self) {
+        match self.selected_preset_index {
+            Some(i) if i >= self.builtin_preset_count => {
+                let removed = self.presets.remove(i);
+                self.selected_preset_index = None;
+                self.rename_preset_references(&removed.name, None);
+                self.save_presets();
+                self.log_messages.push(format!("Deleted preset '{}'", removed.name));
+            }
+            Some(_) => self.log_messages.push("Built-in presets can't be deleted".to_string()),
+            None => self.log_messages.push("Select a preset to delete first".to_string()),
+        }
+    }
+
+    // Writes the selected preset to the working directory for sharing
+    pub fn export_preset(&mut self) {
+        let Some(preset) = self.selected_preset() else {
+            self.log_messages.push("Select a preset to export first".to_string());
+            return;
+        };
+        let dir = std::env::current_dir().unwrap_or_else(|_| std::path::PathBuf::from("."));
+        let message = match presets::export(preset, &dir) {
+            Ok(path) => format!("Exported preset '{}' to {}", preset.name, path.display()),
+            Err(e) => format!("ERROR: {}", e),
+        };
+        self.log_messages.push(message);
+    }
+
+    // Adds a shared *.preset.toml to the user's presets
+    fn import_preset(&mut self, path: &str) {
+        let preset = match presets::import(std::path::Path::new(path)) {
+            Ok(preset) => preset,
+            Err(e) => {
+                self.log_messages.push(format!("ERROR: {}", e));
+                return;
+            }
+        };
+        if let Some(e) = self.preset_error(&preset) {
+            self.log_messages.push(format!("ERROR: Not importing {}", e));
+            return;
+        }
+        if let Err(e) = presets::validate_name(&preset.name, &self.presets) {
+            self.log_messages.push(format!("ERROR: Not importing {}: {}", path, e));
+            return;
+        }
+        self.log_messages.push(format!("Imported preset '{}'", preset.name));
+        self.presets.push(preset);
+        self.save_presets();
+    }
+
+    fn create_preset(&mut self, name: &str) -> Result<(), String> {
+        presets::validate_name(name, &self.presets)?;
+        self.presets.push(Preset::from_settings(name, &self.current_settings()));
+        self.selected_preset_index = Some(self.presets.len() - 1);
+        // Whatever the grid is editing now carries the preset's name
+        self.store_settings();
+        self.save_presets();
+        self.log_messages.push(format!("Saved preset '{}'", name));
+        Ok(())
+    }
+
+    fn finish_rename(&mut self, index: usize, name: &str) -> Result<(), String> {
+        let others: Vec<Preset> = self
+            .presets
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != index)
+            .map(|(_, p)| p.clone())
+            .collect();
+        presets::validate_name(name, &others)?;
+        let old = std::mem::replace(&mut self.presets[index].name, name.to_string());
+        self.rename_preset_references(&old, Some(name));
+        self.save_presets();
+        self.log_messages.push(format!("Renamed preset '{}' to '{}'", old, name));
+        Ok(())
+    }
+
+    // Keeps queue entries and the defaults pointing at a renamed or deleted preset
+    fn rename_preset_references(&mut self, old: &str, new: Option<&str>) {
+        let all_settings = self.queue.iter_mut().map(|item| &mut item.settings).chain(std::iter::once(&mut self.default_settings));
+        for settings in all_settings {
+            if settings.preset.as_deref() == Some(old) {
+                settings.preset = new.map(|name| name.to_string());
+            }
+        }
+    }
+
+    pub fn edit_extra_args(&mut self, action: PromptAction) {
+        let (title, value) = match action {
+            PromptAction::InputArgs => ("Extra input args (before -i)", &self.input_args),
+            _ => ("Extra output args (before the output file)", &self.output_args),
+        };
+        self.text_prompt = Some(TextPrompt { title: title.to_string(), value: value.clone(), action });
+    }
+
+    fn set_extra_args(&mut self, action: PromptAction, text: String) -> Result<(), String> {
+        extra_args::parse(&text)?;
+        match action {
+            PromptAction::InputArgs => self.input_args = text,
+            _ => self.output_args = text,
+        }
+        self.store_settings();
+        Ok(())
+    }
+
+    fn handle_text_prompt_key(&mut self, key: KeyEvent) {
+        let Some(prompt) = self.text_prompt.as_mut() else { return };
+        match key.code {
+            KeyCode::Esc => self.text_prompt = None,
+            KeyCode::Backspace => {
+                prompt.value.pop();
+            }
+            KeyCode::Char(c) => prompt.value.push(c),
+            KeyCode::Enter => {
+                let name = prompt.value.trim().to_string();
+                let result = match prompt.action {
+                    PromptAction::NewPreset => self.create_preset(&name),
+                    PromptAction::RenamePreset(i) => self.finish_rename(i, &name),
+                    PromptAction::InputArgs | PromptAction::OutputArgs => {
+                        let action = prompt.action;
+                        self.set_extra_args(action, name)
+                    }
+                };
+                // On a bad name the prompt stays open so it can be corrected
+                match result {
+                    Ok(()) => self.text_prompt = None,
+                    Err(e) => self.log_messages.push(format!("ERROR: {}", e)),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Temp outputs of an earlier run that was killed mid-encode, in the output folders
+    // of entries added since the last look
+    fn remove_stale_partials(&mut self) {
+        let mut dirs: Vec<std::path::PathBuf> = (0..self.queue.len())
+            .filter_map(|i| self.output_path(i).ok())
+            .filter_map(|path| path.parent().map(|p| p.to_path_buf()))
+            .filter(|dir| !self.cleaned_dirs.contains(dir))
+            .collect();
+        dirs.sort();
+        dirs.dedup();
+        self.cleaned_dirs.extend(dirs.iter().cloned());
+
+        let removed: usize = dirs.iter().map(|dir| output::remove_stale_partials(dir)).sum();
+        if removed > 0 {
+            self.log_messages.push(format!("Removed {} unfinished output file(s) from an earlier run", removed));
+        }
+    }
+
+    // Queues a file with a snapshot of the default settings
+    pub fn add_file(&mut self, path: &str, output_dir: Option<std::path::PathBuf>) {
+        let id = self.new_item_id();
+        self.queue.push(QueueItem::new(id, path.to_string(), self.default_settings.clone(), output_dir));
+        self.log_messages.push(format!("Added to queue: {}", path));
+    }
+
+    // Queues a file, every video file below a directory, or every match of a glob.
+    // Returns how many files were added.
+    pub fn add_path(&mut self, path: &str) -> usize {
+        if presets::is_preset_file(std::path::Path::new(path)) {
+            self.import_preset(path);
+            return 0;
+        }
+        match inputs::expand(path, &self.input_options) {
+            Ok(found) => {
+                let count = found.len();
+                for input in found {
+                    self.add_file(&input.path.to_string_lossy(), input.output_dir);
+                }
+                self.probe_resolutions();
+                self.remove_stale_partials();
+                if std::path::Path::new(path).is_dir() {
+                    self.log_messages.push(format!("Added {} files from {}", count, path));
+                }
+                count
+            }
+            Err(e) => {
+                self.log_messages.push(e);
+                0
+            }
+        }
+    }
+
+    // Dropped or pasted text: one or more paths, possibly quoted, escaped or file:// URIs
+    pub fn handle_paste(&mut self, text: &str) {
+        if let Some(prompt) = self.text_prompt.as_mut() {
+            prompt.value.extend(text.chars().filter(|c| !c.is_control()));
+            return;
+        }
+        if let Some(viewer) = self.log_viewer.as_mut() {
+            if viewer.typing {
+                viewer.search.extend(text.chars().filter(|c| !c.is_control()));
+            }
+            return;
+        }
+        let mut added = 0;
+        let mut imported = 0;
+        for path in paths::parse_pasted_paths(text) {
+            let path_obj = std::path::Path::new(&path);
+            if presets::is_preset_file(path_obj) {
+                imported += 1;
+                self.import_preset(&path);
+                continue;
+            }
+            if path_obj.is_file() && !paths::is_video_file(path_obj) {
+                self.log_messages.push(format!("Not a video file: {}", path));
+                continue;
+            }
+            added += self.add_path(&path);
+        }
+        if added == 0 && imported == 0 {
+            self.log_messages.push("Nothing added from paste".to_string());
+        }
+    }
+
+    // Opens the file picker next to the last queued file, or in the working directory
+    pub fn open_browser(&mut self) {
+        let start = self
+            .queue
+            .last()
+            .and_then(|item| std::path::Path::new(&item.input).parent().map(|p| p.to_path_buf()))
+            .filter(|p| p.is_dir())
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_else(|| std::path::PathBuf::from("."));
+        self.browser = Some(FileBrowser::new(start, self.browser_show_all));
+    }
+
+    fn handle_browser_key(&mut self, key: KeyEvent) {
+        let Some(browser) = self.browser.as_mut() else { return };
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.browser = None,
+            KeyCode::Up => browser.cursor_up(),
+            KeyCode::Down => browser.cursor_down(),
+            KeyCode::Left | KeyCode::Backspace => browser.go_up(),
+            KeyCode::Char(' ') => browser.toggle_selected(),
+            KeyCode::Char('a') => browser.select_all(),
+            KeyCode::Char('.') => browser.toggle_show_all(),
+            KeyCode::Right | KeyCode::Enter => {
+                if let Some(dir) = browser.current().filter(|e| e.is_dir).map(|e| e.path.clone()) {
+                    browser.enter_directory(&dir);
+                } else if key.code == KeyCode::Enter {
+                    let files = browser.take_selection();
+                    self.browser = None;
+                    for file in files {
+                        self.add_path(&file.to_string_lossy());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Opens the log viewer on the selected entry's log, or on the system log
+    pub fn open_log_viewer(&mut self) {
+        let source = self.selected_queue_index.map(|i| self.queue[i].id);
+        self.log_viewer = Some(LogViewer::new(source));
+    }
+
+    // The system log (None) or an entry's log; empty once the entry is removed
+    pub fn log_source_lines(&self, source: Option<usize>) -> &[String] {
+        match source {
+            None => &self.log_messages,
+            Some(id) => self.position_of(id).map(|i| self.queue[i].log.as_slice()).unwrap_or(&[]),
+        }
+    }
+
+    fn handle_log_viewer_key(&mut self, key: KeyEvent) {
+        let Some(mut viewer) = self.log_viewer.take() else { return };
+        let lines = viewer.filtered(self.log_source_lines(viewer.source));
+        let (total, height) = (lines.len(), logview::page_height());
+        if viewer.typing {
+            match key.code {
+                KeyCode::Esc => {
+                    viewer.typing = false;
+                    viewer.search.clear();
+                }
+                KeyCode::Backspace => {
+                    viewer.search.pop();
+                }
+                KeyCode::Char(c) => viewer.search.push(c),
+                KeyCode::Enter => {
+                    viewer.typing = false;
+                    viewer.current_match = None;
+                    viewer.jump_to_match(&lines, true, height);
+                }
+                _ => {}
+            }
+            self.log_viewer = Some(viewer);
+            return;
+        }
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('l') => return,
+            KeyCode::Up | KeyCode::Char('k') => viewer.scroll_up(1, total, height),
+            KeyCode::Down | KeyCode::Char('j') => viewer.scroll_down(1, total, height),
+            KeyCode::PageUp => viewer.scroll_up(height, total, height),
+            KeyCode::PageDown => viewer.scroll_down(height, total, height),
+            KeyCode::Home | KeyCode::Char('g') => viewer.go_to_start(),
+            KeyCode::End | KeyCode::Char('G') => viewer.go_to_end(),
+            KeyCode::Char('/') => {
+                viewer.search.clear();
+                viewer.typing = true;
+            }
+            KeyCode::Char('n') => viewer.jump_to_match(&lines, false, height),
+            KeyCode::Char('N') => viewer.jump_to_match(&lines, true, height),
+            KeyCode::Char('f') => viewer.cycle_level(),
+            KeyCode::Tab | KeyCode::Right | KeyCode::BackTab | KeyCode::Left => {
+                // The system log first, then every entry in queue order
+                let sources: Vec<Option<usize>> = std::iter::once(None).chain(self.queue.iter().map(|item| Some(item.id))).collect();
+                let current = sources.iter().position(|source| *source == viewer.source).unwrap_or(0);
+                let next = if matches!(key.code, KeyCode::Tab | KeyCode::Right) {
+                    (current + 1) % sources.len()
+                } else {
+                    (current + sources.len() - 1) % sources.len()
+                };
+                viewer.source = sources[next];
+                viewer.reset_position();
+            }
+            _ => {}
+        }
+        self.log_viewer = Some(viewer);
+    }
+
+    fn new_item_id(&mut self) -> usize {
+        self.next_item_id += 1;
+        self.next_item_id
+    }
+
+    // Current position of a queue item, which moves when the queue is edited
+    pub fn position_of(&self, id: usize) -> Option<usize> {
+        self.queue.iter().position(|item| item.id == id)
+    }
+
+    pub fn get_next_file(&self) -> Option<usize> {
+        self.queue.iter().position(|item| item.status == JobStatus::Pending)
+    }
+
+    pub fn count_status(&self, status: &JobStatus) -> usize {
+        self.queue.iter().filter(|item| std::mem::discriminant(&item.status) == std::mem::discriminant(status)).count()
+    }
+
+    pub fn output_template(&self) -> &str {
+        &self.output_template_list[self.selected_output_template_index]
+    }
+
+    // Where the entry at `index` will be written with the current template
+    pub fn output_path(&self, index: usize) -> Result<std::path::PathBuf, String> {
+        let item = &self.queue[index];
+        let input = std::path::Path::new(&item.input);
+        let input_dir = input.parent().unwrap_or_else(|| std::path::Path::new("."));
+        let dir = item.output_dir.as_deref().or(self.output_dir.as_deref()).unwrap_or(input_dir);
+        output::render(
+            self.output_template(),
+            &TemplateVars {
+                input,
+                dir,
+                settings: &item.settings,
+                resolution: item.resolution.as_deref(),
+                preset: item.settings.preset.as_deref(),
+            },
+        )
+    }
+
+    // Every command the entry at `index` would run with the current settings, built by
+    // the same functions the jobs use
+    pub fn job_commands(&self, index: usize) -> Result<Vec<JobCommand>, String> {
+        let item = &self.queue[index];
+        let settings = &self.job_settings(index);
+        let threads = self.threads_per_job();
+        let output = self.output_path(index)?;
+        let partial = output::partial_path(&output);
+        let partial_str = partial.to_string_lossy().to_string();
+        let input = std::path::Path::new(&item.input);
+
+        let mut commands = vec![JobCommand::new("probe input", &probe::resolution_command(input))];
+        if let Some(check) = ffmpeg::decode_check_command(&item.input, settings) {
+            commands.push(JobCommand::new("check hardware decoding; the job decodes in software if this fails", &check));
+        }
+        if let Some(target) = self.target_vmaf()
+            && self.needs_search(index)
+        {
+            commands.push(JobCommand::note(format!(
+                "QP is searched first by encoding short samples to reach VMAF {}; -qp below is the starting point",
+                target
+            )));
+        }
+
+        if self.chunk_mode() == chunked::CHUNK_MODE_OFF {
+            commands.push(JobCommand::new("encode", &ffmpeg::encode_command(&item.input, &partial_str, settings, &threads)?));
+        } else {
+            commands.push(JobCommand::new("index frames", &chunked::frame_index_command(&item.input)));
+            if self.chunk_mode() == chunked::CHUNK_MODE_SCENES {
+                commands.push(JobCommand::new("detect scene cuts", &chunked::scene_detect_command(&item.input)));
+            }
+            let work_dir = chunked::chunk_dir(&partial_str);
+            let chunk = chunked::chunk_command(&item.input, settings, &threads, "START", "FRAMES", &work_dir.join("chunk_00000.mkv"))?;
+            commands.push(JobCommand::new(
+                &format!("encode each chunk, {} at a time (START/FRAMES differ per chunk)", self.max_jobs()),
+                &chunk,
+            ));
+            let concat = chunked::concat_command(&work_dir.join("chunks.txt"), &item.input, &partial_str, &settings.audio_bitrate);
+            commands.push(JobCommand::new("join chunks", &concat));
+        }
+
+        if self.verify_mode() != verify::VERIFY_OFF {
+            commands.push(JobCommand::new("verify: probe input", &verify::probe_command(input)));
+            commands.push(JobCommand::new("verify: probe output", &verify::probe_command(&partial)));
+            if self.verify_mode() == verify::VERIFY_DECODE {
+                commands.push(JobCommand::new("verify: decode output", &verify::decode_command(&partial)));
+            }
+        }
+        if self.metrics_mode() != metrics::METRICS_OFF {
+            // Assumes the encode keeps the source size; the job probes the real one
+            let size = item.resolution.as_deref();
+            let filter = metrics::metrics_filter(size, size, &settings.fps, self.vmaf_available);
+            commands.push(JobCommand::new("measure quality", &metrics::measure_command(input, &partial, &filter)));
+        }
+        commands.push(JobCommand::shell(
+            "move into place",
+            format!("mv -f -- {} {}", preview::quote(&partial_str), preview::quote(&output.to_string_lossy())),
+        ));
+        Ok(commands)
+    }
+
+    pub fn toggle_command_preview(&mut self) {
+        self.show_commands = !self.show_commands;
+        self.refresh_command_preview();
+    }
+
+    // Rebuilds the shown commands; called after anything that may change them
+    pub fn refresh_command_preview(&mut self) {
+        self.command_preview = match self.selected_queue_index {
+            Some(index) if self.show_commands => Some(self.job_commands(index)),
+            _ => None,
+        };
+    }
+
+    pub fn copy_commands(&mut self) {
+        let Some(index) = self.selected_queue_index else { return };
+        let message = match self.job_commands(index) {
+            Ok(commands) => match preview::copy_to_clipboard(&preview::script(&commands)) {
+                Ok(()) => format!("[{}] Commands copied to the clipboard", index + 1),
+                Err(e) => format!("ERROR: Cannot copy: {}", e),
+            },
+            Err(e) => format!("[{}] ERROR: {}", index + 1, e),
+        };
+        self.log_messages.push(message);
+    }
+
+    pub fn export_commands(&mut self) {
+        let Some(index) = self.selected_queue_index else { return };
+        let result = self.job_commands(index).and_then(|commands| {
+            let output = self.output_path(index)?;
+            preview::write_script(&output, &commands).map_err(|e| format!("Cannot write script: {}", e))
+        });
+        let message = match result {
+            Ok(path) => format!("[{}] Commands saved to {}", index + 1, path.display()),
+            Err(e) => format!("[{}] ERROR: {}", index + 1, e),
+        };
+        self.log_messages.push(message);
+    }
+
+    pub fn overwrite_policy(&self) -> &str {
+        &self.overwrite_policy_list[self.selected_overwrite_policy_index]
+    }
+
+    // Decides where the entry at `index` may write. Ok(None) means it must not
+    // start now: it was skipped or failed, or the user is being asked.
+    fn resolve_output(&mut self, index: usize) -> Result<Option<std::path::PathBuf>, String> {
+        let output = self.output_path(index)?;
+
+        // Never write over a file that is itself an input of the batch
+        if self.queue.iter().any(|item| output::same_file(std::path::Path::new(&item.input), &output)) {
+            return Err(format!("Refusing to overwrite input file {}", output.display()));
+        }
+        if !output.exists() {
+            return Ok(Some(output));
+        }
+
+        let item = &self.queue[index];
+        let policy = item.overwrite_answer.clone().unwrap_or_else(|| self.overwrite_policy().to_string());
+        match policy.as_str() {
+            output::OVERWRITE_REPLACE => Ok(Some(output)),
+            output::OVERWRITE_RENAME => Ok(Some(output::auto_rename(&output))),
+            output::OVERWRITE_SKIP => {
+                self.log_entry(index, format!("Skipped, output exists: {}", output.display()));
+                self.queue[index].status = JobStatus::Skipped;
+                Ok(None)
+            }
+            _ => {
+                self.overwrite_prompt = Some(OverwritePrompt { id: item.id, output });
+                Ok(None)
+            }
+        }
+    }
+
+    // Applies the answer to the overwrite dialog and carries on with the batch
+    fn handle_overwrite_key(&mut self, key: KeyEvent, tx: &Sender<FfmpegEvent>) {
+        let Some(prompt) = &self.overwrite_prompt else { return };
+        let id = prompt.id;
+        let (answer, for_all) = match key.code {
+            KeyCode::Char('o') => (output::OVERWRITE_REPLACE, false),
+            KeyCode::Char('s') => (output::OVERWRITE_SKIP, false),
+            KeyCode::Char('r') => (output::OVERWRITE_RENAME, false),
+            KeyCode::Char('O') => (output::OVERWRITE_REPLACE, true),
+            KeyCode::Char('S') => (output::OVERWRITE_SKIP, true),
+            KeyCode::Esc => {
+                // Pause: the entry stays pending, running jobs finish
+                self.overwrite_prompt = None;
+                self.is_encoding = false;
+                self.log_messages.push("Batch paused".to_string());
+                return;
+            }
+            _ => return,
+        };
+
+        self.overwrite_prompt = None;
+        if for_all {
+            self.selected_overwrite_policy_index =
+                self.overwrite_policy_list.iter().position(|p| p == answer).unwrap_or(self.selected_overwrite_policy_index);
+        }
+        if let Some(i) = self.position_of(id) {
+            self.queue[i].overwrite_answer = Some(answer.to_string());
+        }
+        self.start_pending_jobs(tx);
+    }
+
+    // Fills in resolutions when the template needs them. A folder of files takes ffprobe
+    // a while, so they trickle in from a background thread; a new batch replaces the
+    // one still running, whose unprobed entries it includes.
+    pub fn probe_resolutions(&mut self) {
+        if !self.output_template().contains("{resolution}") {
+            return;
+        }
+        let inputs: Vec<(usize, String)> =
+            self.queue.iter().filter(|item| item.resolution.is_none()).map(|item| (item.id, item.input.clone())).collect();
+        if inputs.is_empty() {
+            return;
+        }
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            for (id, input) in inputs {
+                if tx.send((id, probe::probe_resolution(std::path::Path::new(&input)))).is_err() {
+                    break; // replaced by a newer batch
+                }
+            }
+        });
+        self.resolution_probe = Some(rx);
+    }
+
+    pub fn poll_resolutions(&mut self) {
+        let Some(rx) = &self.resolution_probe else { return };
+        loop {
+            match rx.try_recv() {
+                Ok((id, resolution)) => {
+                    if let Some(item) = self.queue.iter_mut().find(|item| item.id == id) {
+                        item.resolution = resolution;
+                    }
+                }
+                Err(std::sync::mpsc::TryRecvError::Empty) => return,
+                Err(std::sync::mpsc::TryRecvError::Disconnected) => break,
+            }
+        }
+        self.resolution_probe = None;
+        // The template may put the resolution in the folder name
+        self.remove_stale_partials();
+        self.refresh_command_preview();
+    }
+
+    // Queue positions of unfinished entries that would write the same file as another entry
+    pub fn output_collisions(&self) -> std::collections::HashMap<usize, Vec<usize>> {
+        let active: Vec<usize> = (0..self.queue.len()).filter(|&i| !self.queue[i].status.is_finished()).collect();
+        let outputs: Vec<std::path::PathBuf> = active.iter().filter_map(|&i| self.output_path(i).ok()).collect();
+        if outputs.len() != active.len() {
+            return Default::default(); // template error, reported separately
+        }
+        output::find_collisions(&outputs)
+            .into_iter()
+            .map(|(k, others)| (active[k], others.into_iter().map(|o| active[o]).collect()))
+            .collect()
+    }
+
+    // A message about one entry, in the system log and the entry's own log
+    fn log_entry(&mut self, index: usize, msg: String) {
+        self.log_messages.push(format!("[{}] {}", index + 1, msg));
+        self.queue[index].write_log(msg);
+    }
+
+    // Same for a job whose entry may have been removed from the queue meanwhile
+    fn log_job(&mut self, id: usize, msg: String) {
+        match self.position_of(id) {
+            Some(i) => self.log_entry(i, msg),
+            None => self.log_messages.push(format!("[-] {}", msg)),
+        }
+    }
+
+    // Opens the entry's log file on its first run and marks where each run starts
+    fn begin_entry_log(&mut self, index: usize, settings: &EncodeSettings) {
+        let item = &mut self.queue[index];
+        if item.log_file.is_none()
+            && let Some(dir) = &self.log_dir
+        {
+            match LogFile::create(dir, &item.input, item.id) {
+                Ok(file) => item.log_file = Some(file),
+                Err(e) => self.log_messages.push(format!("[{}] No log file: {}", index + 1, e)),
+            }
+        }
+        let (date, time) = output::now_utc();
+        item.write_log(format!("=== {} {} UTC: {} ===", date, time, settings.summary()));
+    }
+
+    pub fn max_jobs(&self) -> usize {
+        self.max_jobs_list[self.selected_max_jobs_index].parse().unwrap_or(1)
+    }
+
+    pub fn chunk_mode(&self) -> &str {
+        &self.chunk_mode_list[self.selected_chunk_mode_index]
+    }
+
+    pub fn verify_mode(&self) -> &str {
+        &self.verify_list[self.selected_verify_index]
+    }
+
+    pub fn target_vmaf(&self) -> Option<f64> {
+        self.target_vmaf_list[self.selected_target_vmaf_index].parse().ok()
+    }
+
+    pub fn metrics_mode(&self) -> &str {
+        &self.metrics_list[self.selected_metrics_index]
+    }
+
+    // In chunked mode the workers split a single file, so files run one at a time
+    pub fn concurrent_files(&self) -> usize {
+        if self.chunk_mode() == chunked::CHUNK_MODE_OFF { self.max_jobs() } else { 1 }
+    }
+
+    pub fn threads_per_job(&self) -> Option<String> {
+        let threads = &self.threads_list[self.selected_threads_index];
+        if threads == "Auto" { None } else { Some(threads.clone()) }
+    }
+
+    // Render node for VAAPI/QSV encoders; None lets the driver pick
+    pub fn hw_device(&self) -> Option<String> {
+        let device = &self.hw_device_list[self.selected_hw_device_index];
+        if device == hwaccel::DEVICE_AUTO { None } else { Some(device.clone()) }
+    }
+
+    // An entry's settings plus the machine settings that apply when it runs
+    fn job_settings(&self, index: usize) -> EncodeSettings {
+        EncodeSettings { hw_device: self.hw_device(), ..self.queue[index].settings.clone() }
+    }
+
+    // Fraction of the whole queue that is finished, counting running jobs partially
+    pub fn overall_progress(&self) -> f64 {
+        if self.queue.is_empty() {
+            return 0.0;
+        }
+        let finished = self.queue.iter().filter(|item| item.status.is_finished()).count() as f64;
+        let running: f64 = self.active_jobs.iter().map(|j| j.progress).sum();
+        ((finished + running) / self.queue.len() as f64).min(1.0)
+    }
+
+    // Hands queue entries to workers until the pool is full or the queue is exhausted
+    pub fn start_pending_jobs(&mut self, tx: &Sender<FfmpegEvent>) {
+        while self.active_jobs.len() < self.concurrent_files() && self.overwrite_prompt.is_none() {
+            let Some(index) = self.get_next_file() else {
+                break;
+            };
+            // Resolved before the search too, so entries that end up skipped don't pay for one
+            let output = match self.resolve_output(index) {
+                Ok(Some(path)) => path,
+                Ok(None) => continue,
+                Err(e) => {
+                    self.log_entry(index, format!("ERROR: {}", e));
+                    self.queue[index].status = JobStatus::Failed(e);
+                    continue;
+                }
+            };
+            if let Some(target) = self.target_vmaf()
+                && self.needs_search(index)
+            {
+                self.start_search(index, target, tx);
+                continue;
+            }
+            let settings = self.job_settings(index);
+            let item = &mut self.queue[index];
+            item.start_run(JobStatus::Encoding);
+            item.metrics = None;
+            let id = item.id;
+            let input = item.input.clone();
+
+            self.begin_entry_log(index, &settings);
+            self.log_entry(index, format!("Starting encoding: {}", input));
+
+            // Mirrored output trees may not exist yet
+            if let Some(parent) = output.parent()
+                && let Err(e) = std::fs::create_dir_all(parent)
+            {
+                self.log_entry(index, format!("Cannot create {}: {}", parent.display(), e));
+            }
+
+            let partial = output::partial_path(&output);
+            let _ = std::fs::remove_file(&partial); // leftover from an earlier attempt
+            let job = ActiveJob::new(id, output);
+            let cancel = job.cancel.clone();
+            let output = partial.to_string_lossy().to_string();
+            self.active_jobs.push(job);
+            if self.chunk_mode() == chunked::CHUNK_MODE_OFF {
+                ffmpeg::start_encoding(id, input, output, settings, self.threads_per_job(), cancel, tx.clone());
+            } else {
+                chunked::start_chunked_encoding(
+                    id,
+                    input,
+                    output,
+                    settings,
+                    self.chunk_mode().to_string(),
+                    self.max_jobs(),
+                    self.threads_per_job(),
+                    cancel,
+                    tx.clone(),
+                );
+            }
+        }
+
+        if self.active_jobs.is_empty() && self.overwrite_prompt.is_none() {
+            self.is_encoding = false;
+        }
+    }
+
+    pub fn handle_ffmpeg_event(&mut self, event: FfmpegEvent, tx: &Sender<FfmpegEvent>) {
+        // Found QPs and fallback settings change the commands; progress and output don't
+        let changes_commands = !matches!(event, FfmpegEvent::Progress(..) | FfmpegEvent::Output(..));
+        self.apply_ffmpeg_event(event, tx);
+        if changes_commands {
+            self.refresh_command_preview();
+        }
+    }
+
+    fn apply_ffmpeg_event(&mut self, event: FfmpegEvent, tx: &Sender<FfmpegEvent>) {
+        match event {
+            FfmpegEvent::Progress(id, p, fps, speed, bitrate, time) => {
+                if let Some(job) = self.active_jobs.iter_mut().find(|j| j.id == id) {
+                    job.progress = p;
+                    job.fps = fps;
+                    job.speed = speed;
+                    job.bitrate = bitrate;
+                    job.time = time;
+                }
+            }
+            FfmpegEvent::Log(id, msg) => {
+                if let Some(i) = self.position_of(id) {
+                    self.queue[i].output.push(msg.clone());
+                }
+                self.log_job(id, msg);
+                let excess = self.log_messages.len().saturating_sub(logs::SYSTEM_HISTORY);
+                self.log_messages.drain(..excess);
+            }
+            FfmpegEvent::Output(id, line) => {
+                if let Some(i) = self.position_of(id) {
+                    let item = &mut self.queue[i];
+                    item.output.push(line.clone());
+                    item.write_log(line);
+                }
+            }
+            FfmpegEvent::Done(id) => {
+                let Some(i) = self.position_of(id) else {
+                    self.finish_job(id, tx);
+                    return;
+                };
+                if self.verify_mode() == verify::VERIFY_OFF {
+                    self.measure_or_finish(id, tx);
+                    return;
+                }
+                let Some(job) = self.active_jobs.iter().find(|j| j.id == id) else {
+                    return;
+                };
+                let Some(files) = &job.files else { return };
+
+                // The job keeps its worker slot until the checks are done
+                let item = &mut self.queue[i];
+                item.status = JobStatus::Verifying;
+                verify::start_verification(
+                    id,
+                    item.input.clone(),
+                    files.partial.to_string_lossy().to_string(),
+                    item.settings.container.clone(),
+                    self.verify_mode().to_string(),
+                    job.cancel.clone(),
+                    tx.clone(),
+                );
+            }
+            FfmpegEvent::Verified(id) => {
+                self.log_job(id, "Output verified".to_string());
+                self.measure_or_finish(id, tx);
+            }
+            FfmpegEvent::QualityFound(id, qp) => {
+                self.active_jobs.retain(|j| j.id != id);
+                if let Some(i) = self.position_of(id) {
+                    self.log_entry(i, format!("Using QP {}", qp));
+                    let item = &mut self.queue[i];
+                    item.settings.qp = qp.clone();
+                    item.target_qp = Some(qp);
+                    item.status = JobStatus::Pending;
+                    // Otherwise the next edit in the grid would write the old QP back
+                    if self.selected_queue_index == Some(i) {
+                        let settings = item.settings.clone();
+                        self.load_settings(&settings);
+                    }
+                }
+                if self.is_encoding {
+                    self.start_pending_jobs(tx);
+                }
+            }
+            FfmpegEvent::Measured(id, scores) => {
+                if let Some(i) = self.position_of(id) {
+                    if let Some(scores) = &scores {
+                        self.log_entry(i, scores.summary());
+                    }
+                    self.queue[i].metrics = scores;
+                }
+                self.finish_job(id, tx);
+            }
+            FfmpegEvent::VerifyFailed(id, msg) => {
+                self.log_job(id, format!("VERIFICATION FAILED: {}", msg));
+                let Some(job) = self.active_jobs.iter().position(|j| j.id == id) else {
+                    return;
+                };
+                let job = self.active_jobs.remove(job);
+                job.discard_partial();
+                if let Some(i) = self.position_of(id) {
+                    self.retry_or_fail(i, JobStatus::VerifyFailed(msg));
+                }
+                // Same as a failed encode: the rest of the batch carries on
+                self.continue_batch(tx);
+            }
+            FfmpegEvent::Error(id, msg) => {
+                self.log_job(id, format!("ERROR: {}", msg));
+
+                // A job can report more than one error; only the first one ends it
+                let Some(job) = self.active_jobs.iter().position(|j| j.id == id) else {
+                    return;
+                };
+                let job = self.active_jobs.remove(job);
+                job.discard_partial();
+                let cancelled = job.cancel.load(Ordering::SeqCst);
+                if let Some(i) = self.position_of(id) {
+                    if cancelled {
+                        self.queue[i].status = JobStatus::Cancelled;
+                    } else {
+                        self.retry_or_fail(i, JobStatus::Failed(msg));
+                    }
+                }
+
+                // A failed or cancelled entry doesn't stop the rest of the batch
+                self.continue_batch(tx);
+            }
+        }
+    }
+
+    // Records a failed run and puts the entry back in line while the retry policy
+    // allows: first with the same settings, then with each fallback step
+    fn retry_or_fail(&mut self, index: usize, failure: JobStatus) {
+        let (JobStatus::Failed(error) | JobStatus::VerifyFailed(error)) = &failure else {
+            return;
+        };
+        let retries = self.retries_list[self.selected_retries_index].parse::<usize>().unwrap_or(0);
+        let fallback = self.fallback_list[self.selected_fallback_index] != retry::FALLBACK_OFF;
+        let next_step = if fallback { self.fallback_step(&self.queue[index].settings) } else { None };
+
+        let mut messages = Vec::new();
+        let item = &mut self.queue[index];
+        item.diagnosis = diagnostics::diagnose(&item.output, error);
+        if let Some(diagnosis) = &item.diagnosis {
+            messages.push(format!("{}. Fix: {}", diagnosis.problem, diagnosis.fix));
+            self.last_diagnosed = Some(item.id);
+        }
+        item.attempts.push(Attempt { summary: item.settings.summary(), error: error.clone() });
+        let attempt = item.attempts.len() + 1;
+        if item.retries_used < retries {
+            item.retries_used += 1;
+            item.status = JobStatus::Pending;
+            messages.push(format!("Retrying, attempt {} ({}/{} retries)", attempt, item.retries_used, retries));
+        } else if let Some((settings, change)) = next_step {
+            // A different encoder needs its own QP search
+            if settings.encoder != item.settings.encoder {
+                item.target_qp = None;
+            }
+            item.settings = settings;
+            item.retries_used = 0;
+            item.status = JobStatus::Pending;
+            messages.push(format!("Retrying with fallback {}, attempt {}", change, attempt));
+            if self.selected_queue_index == Some(index) {
+                let settings = item.settings.clone();
+                self.load_settings(&settings);
+            }
+        } else {
+            item.status = failure;
+        }
+        for message in messages {
+            self.log_entry(index, message);
+        }
+    }
+
+    // Diagnosis for the dashboard: the selected entry's, or the latest one
+    pub fn shown_diagnosis(&self) -> Option<(usize, &Diagnosis)> {
+        let index = self
+            .selected_queue_index
+            .filter(|&i| self.queue[i].diagnosis.is_some())
+            .or_else(|| self.last_diagnosed.and_then(|id| self.position_of(id)))?;
+        self.queue[index].diagnosis.as_ref().map(|diagnosis| (index, diagnosis))
+    }
+
+    // First fallback step whose encoder can run here
+    fn fallback_step(&self, settings: &EncodeSettings) -> Option<(EncodeSettings, String)> {
+        let mut current = settings.clone();
+        let mut changes = Vec::new();
+        while let Some((next, change)) = retry::fallback(&current) {
+            changes.push(change);
+            if self.encoder_problem(&next.encoder).is_none() {
+                return Some((next, changes.join(", ")));
+            }
+            current = next;
+        }
+        None
+    }
+
+    // Whether the entry's QP is still to be found. Encoders whose quality comes from
+    // the profile have no QP to search for.
+    fn needs_search(&self, index: usize) -> bool {
+        let item = &self.queue[index];
+        item.target_qp.is_none() && codecs::uses_qp(&item.settings.encoder)
+    }
+
+    // Occupies a worker with the QP search; the encode follows once a QP is found
+    fn start_search(&mut self, index: usize, target: f64, tx: &Sender<FfmpegEvent>) {
+        let settings = self.job_settings(index);
+        let item = &mut self.queue[index];
+        item.start_run(JobStatus::Searching);
+        let id = item.id;
+        let input = item.input.clone();
+        self.begin_entry_log(index, &settings);
+        self.log_entry(index, format!("Starting quality search: {}", input));
+
+        let job = ActiveJob::searching(id);
+        let cancel = job.cancel.clone();
+        self.active_jobs.push(job);
+        search::start_search(id, input, settings, target, self.threads_per_job(), cancel, tx.clone());
+    }
+
+    // Stops every running job, so no ffmpeg outlives the program
+    pub fn cancel_all(&mut self) {
+        self.is_encoding = false;
+        for job in &self.active_jobs {
+            job.cancel.store(true, Ordering::SeqCst);
+        }
+    }
+
+    // Runs the quality pass when it's enabled, otherwise completes the job right away
+    fn measure_or_finish(&mut self, id: usize, tx: &Sender<FfmpegEvent>) {
+        let (Some(i), Some(job)) = (self.position_of(id), self.active_jobs.iter().find(|j| j.id == id)) else {
+            self.finish_job(id, tx);
+            return;
+        };
+        let Some(files) = &job.files else {
+            self.finish_job(id, tx);
+            return;
+        };
+        if self.metrics_mode() == metrics::METRICS_OFF {
+            self.finish_job(id, tx);
+            return;
+        }
+
+        let item = &mut self.queue[i];
+        item.status = JobStatus::Measuring;
+        metrics::start_measurement(
+            id,
+            item.input.clone(),
+            files.partial.to_string_lossy().to_string(),
+            item.settings.fps.clone(),
+            job.cancel.clone(),
+            tx.clone(),
+        );
+    }
 
-    // Encoding state
-    pub is_encoding: bool,
-    pub progress: f64,
-    pub fps: String,
-    pub speed: String,
-    pub bitrate: String,
-    pub time: String,
-    pub log_messages: Vec<String>,
-}
+    // Moves a successful job's output into place and hands out the next files
+    fn finish_job(&mut self, id: usize, tx: &Sender<FfmpegEvent>) {
+        let Some(job) = self.active_jobs.iter().position(|j| j.id == id) else {
+            return;
+        };
+        let job = self.active_jobs.remove(job);
+        let Some(files) = &job.files else { return };
 
-impl App {
-    pub fn new(args: Vec<String>) -> Self {
-        let qp_list: Vec<String> = (0..=53).map(|i| i.to_string()).collect();
-        let audio_bitrate_list = vec![
-            "128k".to_string(),
-            "160k".to_string(),
-            "192k".to_string(),
-            "256k".to_string(),
-            "320k".to_string(),
-        ];
-        let container_list = vec![
-            "mp4".to_string(),
-            "mkv".to_string(),
-            "avi".to_string(),
-            "webm".to_string(),
-            "gif".to_string(),
-            "mov".to_string(),
-        ];
-        let fps_list = vec![
-            "Same".to_string(),
-            "24".to_string(),
-            "30".to_string(),
-            "60".to_string(),
-            "120".to_string(),
-            "144".to_string(),
-        ];
+        // A file that appeared at the output path during the encode was never checked
+        // against IF EXISTS; only Overwrite may replace it, otherwise the encode is kept
+        // under a new name
+        let mut destination = files.output.clone();
+        if destination.exists() && !files.replaces {
+            let policy = match self.queue.iter().find(|item| item.id == id) {
+                Some(item) => item.overwrite_answer.clone().unwrap_or_else(|| self.overwrite_policy().to_string()),
+                None => self.overwrite_policy().to_string(),
+            };
+            if policy != output::OVERWRITE_REPLACE {
+                destination = output::auto_rename(&destination);
+                self.log_job(id, format!("{} appeared during the encode, saving as {}", files.output.display(), destination.display()));
+            }
+        }
+
+        let status = match std::fs::rename(&files.partial, &destination) {
+            Ok(()) => {
+                self.log_job(id, "Encoding Finished!".to_string());
+                if let Some(item) = self.queue.iter().find(|item| item.id == id)
+                    && let Some(scores) = &item.metrics
+                {
+                    let message = match metrics::write_report(&destination, &item.input, &item.settings, scores) {
+                        Ok(path) => format!("Report saved: {}", path.display()),
+                        Err(e) => format!("Cannot write report: {}", e),
+                    };
+                    self.log_job(id, message);
+                }
+                JobStatus::Done
+            }
+            Err(e) => {
+                let _ = std::fs::remove_file(&files.partial);
+                let msg = format!("Cannot move output into place at {}: {}", destination.display(), e);
+                self.log_job(id, format!("ERROR: {}", msg));
+                JobStatus::Failed(msg)
+            }
+        };
+        if let Some(i) = self.position_of(id) {
+            self.queue[i].status = status;
+        }
+        self.continue_batch(tx);
+    }
 
-        let mut queue = Vec::new();
-        let mut log_messages = Vec::new();
-
-        for arg in args {
-            let path_obj = std::path::Path::new(&arg);
-            if path_obj.exists() {
-                 let parent = path_obj.parent().unwrap_or_else(|| std::path::Path::new("."));
-                 let stem = path_obj.file_stem().unwrap_or_default().to_string_lossy();
-                 let ext = path_obj.extension().unwrap_or_default().to_string_lossy();
-                 let output_filename = format!("{}_encoded.{}", stem, ext);
-                 let output = parent.join(output_filename).to_string_lossy().to_string();
-                 
-                 queue.push((arg.clone(), output));
-                 log_messages.push(format!("Added to queue: {}", arg));
+    // Hands out the next entries after a job ended, and reports when the batch is through
+    fn continue_batch(&mut self, tx: &Sender<FfmpegEvent>) {
+        if self.is_encoding {
+            self.start_pending_jobs(tx);
+        }
+        if self.active_jobs.is_empty() && self.get_next_file().is_none() {
+            self.is_encoding = false;
+            let failed = self.queue.iter().filter(|item| matches!(item.status, JobStatus::Failed(_) | JobStatus::VerifyFailed(_))).count();
+            if failed == 0 {
+                self.log_messages.push("All files processed!".to_string());
             } else {
-                 log_messages.push(format!("File not found: {}", arg));
+                self.log_messages.push(format!("All files processed, {} failed", failed));
             }
         }
+    }
 
-        Self {
-            should_quit: false,
-            focus: Focus::Encoder,
-            
-            queue,
-            current_file_index: 0,
-            completed_files: Vec::new(),
+    pub fn current_settings(&self) -> EncodeSettings {
+        let mut settings = EncodeSettings {
+            encoder: self.encoders[self.selected_encoder_index].clone(),
+            container: self.container_list[self.selected_container_index].clone(),
+            qp: self.qp_list[self.selected_qp_index].clone(),
+            fps: self.fps_list[self.selected_fps_index].clone(),
+            audio_bitrate: self.audio_bitrate_list[self.selected_audio_bitrate_index].clone(),
+            pix_fmt: self.pix_fmt_list[self.selected_pix_fmt_index].clone(),
+            profile: self.profile_list[self.selected_profile_index].clone(),
+            hw_decode: self.hw_decode_list[self.selected_hw_decode_index].clone(),
+            input_args: self.input_args.clone(),
+            output_args: self.output_args.clone(),
+            preset: None,
+            hw_device: None,
+        };
+        // Still named after the preset only while nothing has been changed by hand
+        if let Some(preset) = self.selected_preset().filter(|p| p.matches(&settings)) {
+            settings.preset = Some(preset.name.clone());
+        }
+        settings
+    }
 
-            encoders: vec![
-                "libx264".to_string(),
-                "libx265".to_string(),
-                "libvpx-vp9".to_string(),
-                "libaom-av1".to_string(),
-                "h264_nvenc".to_string(),
-                "hevc_nvenc".to_string(),
-                "av1_nvenc".to_string(),
-            ],
-            selected_encoder_index: 0,
-            container_list,
-            selected_container_index: 0,
-            qp_list,
-            selected_qp_index: 23, // Default to something reasonable like 23
-            fps_list,
-            selected_fps_index: 0, // Default "Same"
-            audio_bitrate_list,
-            selected_audio_bitrate_index: 0,
-            
-            is_encoding: false,
-            progress: 0.0,
-            fps: String::from("0"),
-            speed: String::from("0x"),
-            bitrate: String::from("0kbits/s"),
-            time: String::from("00:00:00"),
-            log_messages,
+    // Sets the grid to show the given settings; values missing from a list are left as they are
+    pub fn load_settings(&mut self, settings: &EncodeSettings) {
+        fn position(list: &[String], value: &str, current: usize) -> usize {
+            list.iter().position(|v| v == value).unwrap_or(current)
         }
+        self.selected_encoder_index = position(&self.encoders, &settings.encoder, self.selected_encoder_index);
+        self.selected_hw_decode_index = position(&self.hw_decode_list, &settings.hw_decode, self.selected_hw_decode_index);
+        self.selected_container_index = position(&self.container_list, &settings.container, self.selected_container_index);
+        self.selected_qp_index = position(&self.qp_list, &settings.qp, self.selected_qp_index);
+        self.selected_fps_index = position(&self.fps_list, &settings.fps, self.selected_fps_index);
+        self.selected_audio_bitrate_index =
+            position(&self.audio_bitrate_list, &settings.audio_bitrate, self.selected_audio_bitrate_index);
+        self.selected_pix_fmt_index = position(&self.pix_fmt_list, &settings.pix_fmt, self.selected_pix_fmt_index);
+        self.refresh_profiles();
+        self.selected_profile_index = position(&self.profile_list, &settings.profile, self.selected_profile_index);
+        self.input_args = settings.input_args.clone();
+        self.output_args = settings.output_args.clone();
+        self.selected_preset_index = settings.preset.as_ref().and_then(|name| self.presets.iter().position(|p| p.name == *name));
     }
 
-    pub fn get_current_file(&self) -> Option<(String, String)> {
-        if self.current_file_index < self.queue.len() {
-            Some(self.queue[self.current_file_index].clone())
-        } else {
-            None
+    // Running and finished entries keep the settings they ran with
+    pub fn is_editable(&self, index: usize) -> bool {
+        !matches!(
+            self.queue[index].status,
+            JobStatus::Searching | JobStatus::Encoding | JobStatus::Verifying | JobStatus::Measuring | JobStatus::Done
+        )
+    }
+
+    // Writes the grid back to whatever it is editing
+    fn store_settings(&mut self) {
+        let settings = self.current_settings();
+        if settings.preset.is_none() {
+            self.selected_preset_index = None;
+        }
+        match self.selected_queue_index {
+            None => self.default_settings = settings,
+            Some(i) if self.is_editable(i) => {
+                let item = &mut self.queue[i];
+                // A QP found for other settings doesn't hold for these
+                if item.settings != settings {
+                    item.target_qp = None;
+                }
+                item.settings = settings;
+            }
+            Some(i) => {
+                let started = self.queue[i].settings.clone();
+                self.load_settings(&started);
+                self.log_messages.push(format!("[{}] Already {}, settings can't be changed", i + 1, self.queue[i].status.label()));
+            }
+        }
+    }
+
+    fn select_queue_entry(&mut self, index: Option<usize>) {
+        self.selected_queue_index = index;
+        let settings = match index {
+            Some(i) => self.queue[i].settings.clone(),
+            None => self.default_settings.clone(),
+        };
+        self.load_settings(&settings);
+    }
+
+    pub fn next_queue_entry(&mut self) {
+        let next = match self.selected_queue_index {
+            None if !self.queue.is_empty() => Some(0),
+            Some(i) if i + 1 < self.queue.len() => Some(i + 1),
+            _ => None,
+        };
+        self.select_queue_entry(next);
+    }
+
+    pub fn previous_queue_entry(&mut self) {
+        let previous = match self.selected_queue_index {
+            None if !self.queue.is_empty() => Some(self.queue.len() - 1),
+            Some(i) if i > 0 => Some(i - 1),
+            _ => None,
+        };
+        self.select_queue_entry(previous);
+    }
+
+    // Queue editing; everything below acts on the selected entry
+
+    pub fn remove_selected(&mut self) {
+        let Some(i) = self.selected_queue_index else { return };
+        let item = self.queue.remove(i);
+        if let Some(job) = self.active_jobs.iter().find(|j| j.id == item.id) {
+            job.cancel.store(true, Ordering::SeqCst);
+        }
+        self.log_messages.push(format!("Removed from queue: {}", item.input));
+
+        let next = if self.queue.is_empty() { None } else { Some(i.min(self.queue.len() - 1)) };
+        self.select_queue_entry(next);
+    }
+
+    pub fn move_selected_up(&mut self) {
+        if let Some(i) = self.selected_queue_index
+            && i > 0
+        {
+            self.queue.swap(i, i - 1);
+            self.selected_queue_index = Some(i - 1);
+        }
+    }
+
+    pub fn move_selected_down(&mut self) {
+        if let Some(i) = self.selected_queue_index
+            && i + 1 < self.queue.len()
+        {
+            self.queue.swap(i, i + 1);
+            self.selected_queue_index = Some(i + 1);
+        }
+    }
+
+    pub fn duplicate_selected(&mut self) {
+        let Some(i) = self.selected_queue_index else { return };
+        let id = self.new_item_id();
+        let copy = self.queue[i].duplicate(id);
+        self.queue.insert(i + 1, copy);
+        self.select_queue_entry(Some(i + 1));
+    }
+
+    pub fn toggle_skip_selected(&mut self) {
+        let Some(i) = self.selected_queue_index else { return };
+        let item = &mut self.queue[i];
+        item.status = match item.status {
+            JobStatus::Pending => JobStatus::Skipped,
+            JobStatus::Skipped => JobStatus::Pending,
+            ref other => other.clone(),
+        };
+    }
+
+    pub fn cancel_selected(&mut self) {
+        let Some(i) = self.selected_queue_index else { return };
+        let id = self.queue[i].id;
+        if let Some(job) = self.active_jobs.iter().find(|j| j.id == id) {
+            job.cancel.store(true, Ordering::SeqCst);
+            self.log_entry(i, "Cancelling...".to_string());
+        }
+    }
+
+    // Puts a failed, cancelled or skipped entry back in line
+    pub fn retry_selected(&mut self, tx: &Sender<FfmpegEvent>) {
+        let Some(i) = self.selected_queue_index else { return };
+        if matches!(
+            self.queue[i].status,
+            JobStatus::Failed(_) | JobStatus::VerifyFailed(_) | JobStatus::Cancelled | JobStatus::Skipped
+        ) {
+            self.queue[i].status = JobStatus::Pending;
+            self.queue[i].retries_used = 0;
+            if self.is_encoding {
+                self.start_pending_jobs(tx);
+            }
+        }
+    }
+
+    pub fn retry_failed(&mut self, tx: &Sender<FfmpegEvent>) {
+        let mut retried = 0;
+        for item in self.queue.iter_mut().filter(|item| matches!(item.status, JobStatus::Failed(_) | JobStatus::VerifyFailed(_))) {
+            item.status = JobStatus::Pending;
+            item.retries_used = 0;
+            retried += 1;
+        }
+        self.log_messages.push(format!("Retrying {} failed entries", retried));
+        if self.is_encoding {
+            self.start_pending_jobs(tx);
+        }
+    }
+
+    pub fn clear_completed(&mut self) {
+        let selected_id = self.selected_queue_index.map(|i| self.queue[i].id);
+        self.queue.retain(|item| item.status != JobStatus::Done);
+        let selected = selected_id.and_then(|id| self.position_of(id));
+        self.select_queue_entry(selected);
+    }
+
+    // Error if the selected pixel format can't be produced by the selected encoder
+    pub fn pix_fmt_error(&self) -> Option<String> {
+        let settings = self.current_settings();
+        codecs::resolve_pix_fmt(&settings.encoder, &settings.profile, &settings.pix_fmt).err()
+    }
+
+    // Error if the selected encoder's output can't go into the selected container
+    pub fn container_error(&self) -> Option<String> {
+        let settings = self.current_settings();
+        codecs::container_error(&settings.encoder, &settings.container)
+    }
+
+    // First reason the pending entries can't start: a pixel format or container the
+    // encoder can't produce, a broken template, or two entries writing the same file
+    pub fn queue_error(&self) -> Option<String> {
+        if let Err(e) = output::validate_template(self.output_template()) {
+            return Some(e);
+        }
+        if self.resolution_probe.is_some() {
+            return Some("Still reading input resolutions for the output names".to_string());
+        }
+        let pix_fmt_error = self.queue.iter().enumerate().filter(|(_, item)| item.status == JobStatus::Pending).find_map(|(i, item)| {
+            let s = &item.settings;
+            codecs::resolve_pix_fmt(&s.encoder, &s.profile, &s.pix_fmt)
+                .err()
+                .or_else(|| codecs::container_error(&s.encoder, &s.container))
+                .map(|e| format!("[{}] {}", i + 1, e))
+        });
+        if pix_fmt_error.is_some() {
+            return pix_fmt_error;
+        }
+        let encoder_error = self.queue.iter().enumerate().filter(|(_, item)| item.status == JobStatus::Pending).find_map(|(i, item)| {
+            self.encoder_problem(&item.settings.encoder)
+                .map(|reason| format!("[{}] {} is unavailable: {}", i + 1, item.settings.encoder, reason))
+        });
+        if encoder_error.is_some() {
+            return encoder_error;
+        }
+        let mut collisions: Vec<(usize, Vec<usize>)> = self.output_collisions().into_iter().collect();
+        collisions.sort();
+        collisions.first().map(|(i, others)| {
+            let others: Vec<String> = others.iter().map(|o| format!("[{}]", o + 1)).collect();
+            format!("[{}] writes the same output file as {}", i + 1, others.join(", "))
+        })
+    }
+
+    pub fn playback_warning(&self) -> Option<String> {
+        let settings = self.current_settings();
+        codecs::playback_warning(&settings.encoder, &settings.container, &settings.pix_fmt)
+    }
+
+    // A decode method this ffmpeg doesn't list; jobs would fall back to software decoding
+    pub fn decode_warning(&self) -> Option<String> {
+        let decode = &self.hw_decode_list[self.selected_hw_decode_index];
+        if decode == hwaccel::DECODE_OFF || decode == hwaccel::DECODE_AUTO || self.hwaccels.is_empty() || self.hwaccels.contains(decode) {
+            return None;
         }
+        Some(format!("This ffmpeg has no {} decoding, jobs will decode in software", decode))
     }
 
     pub fn next_focus(&mut self) {
         self.focus = match self.focus {
-            Focus::Encoder => Focus::Container,
+            Focus::Preset => Focus::Encoder,
+            Focus::Encoder => Focus::HwDecode,
+            Focus::HwDecode => Focus::Container,
             Focus::Container => Focus::Qp,
-            Focus::Qp => Focus::Fps,
+            Focus::Qp => Focus::TargetVmaf,
+            Focus::TargetVmaf => Focus::Fps,
             Focus::Fps => Focus::AudioBitrate,
-            Focus::AudioBitrate => Focus::Submit,
-            Focus::Submit => Focus::Encoder,
+            Focus::AudioBitrate => Focus::PixFmt,
+            Focus::PixFmt => Focus::Profile,
+            Focus::Profile => Focus::InputArgs,
+            Focus::InputArgs => Focus::OutputArgs,
+            Focus::OutputArgs => Focus::MaxJobs,
+            Focus::MaxJobs => Focus::Threads,
+            Focus::Threads => Focus::HwDevice,
+            Focus::HwDevice => Focus::ChunkMode,
+            Focus::ChunkMode => Focus::Verify,
+            Focus::Verify => Focus::OutputTemplate,
+            Focus::OutputTemplate => Focus::Overwrite,
+            Focus::Overwrite => Focus::Metrics,
+            Focus::Metrics => Focus::Retries,
+            Focus::Retries => Focus::Fallback,
+            Focus::Fallback => Focus::Submit,
+            Focus::Submit => Focus::Preset,
         };
     }
 
     pub fn previous_focus(&mut self) {
         self.focus = match self.focus {
-            Focus::Encoder => Focus::Submit,
-            Focus::Container => Focus::Encoder,
+            Focus::Preset => Focus::Submit,
+            Focus::Encoder => Focus::Preset,
+            Focus::Container => Focus::HwDecode,
+            Focus::HwDecode => Focus::Encoder,
             Focus::Qp => Focus::Container,
-            Focus::Fps => Focus::Qp,
+            Focus::TargetVmaf => Focus::Qp,
+            Focus::Fps => Focus::TargetVmaf,
             Focus::AudioBitrate => Focus::Fps,
-            Focus::Submit => Focus::AudioBitrate,
+            Focus::PixFmt => Focus::AudioBitrate,
+            Focus::MaxJobs => Focus::OutputArgs,
+            Focus::OutputArgs => Focus::InputArgs,
+            Focus::InputArgs => Focus::Profile,
+            Focus::Profile => Focus::PixFmt,
+            Focus::Threads => Focus::MaxJobs,
+            Focus::ChunkMode => Focus::HwDevice,
+            Focus::HwDevice => Focus::Threads,
+            Focus::Verify => Focus::ChunkMode,
+            Focus::OutputTemplate => Focus::Verify,
+            Focus::Overwrite => Focus::OutputTemplate,
+            Focus::Metrics => Focus::Overwrite,
+            Focus::Retries => Focus::Metrics,
+            Focus::Fallback => Focus::Retries,
+            Focus::Submit => Focus::Fallback,
         };
     }
 
+    pub fn next_preset(&mut self) {
+        self.step_preset(true);
+    }
+
+    pub fn previous_preset(&mut self) {
+        self.step_preset(false);
+    }
+
+    // Moves to the neighbouring preset and loads it into the grid. "Custom" (no
+    // preset) is part of the cycle; presets the grid can't show are skipped.
+    fn step_preset(&mut self, forward: bool) {
+        let slots = self.presets.len() + 1; // slot 0 is "custom"
+        let mut slot = self.selected_preset_index.map_or(0, |i| i + 1);
+        loop {
+            slot = if forward { (slot + 1) % slots } else { (slot + slots - 1) % slots };
+            if slot == 0 {
+                self.selected_preset_index = None;
+                return;
+            }
+            let preset = self.presets[slot - 1].clone();
+            match self.preset_error(&preset) {
+                None => {
+                    self.load_settings(&preset.settings());
+                    return;
+                }
+                Some(e) => self.log_messages.push(format!("Skipping {}", e)),
+            }
+        }
+    }
+
+    // Encoders that failed the startup check are skipped
     pub fn next_encoder(&mut self) {
-        self.selected_encoder_index = (self.selected_encoder_index + 1) % self.encoders.len();
+        for _ in 0..self.encoders.len() {
+            self.selected_encoder_index = (self.selected_encoder_index + 1) % self.encoders.len();
+            if self.encoder_problem(&self.encoders[self.selected_encoder_index]).is_none() {
+                break;
+            }
+        }
+        self.refresh_profiles();
     }
 
     pub fn previous_encoder(&mut self) {
-        if self.selected_encoder_index > 0 {
-            self.selected_encoder_index -= 1;
+        for _ in 0..self.encoders.len() {
+            if self.selected_encoder_index > 0 {
+                self.selected_encoder_index -= 1;
+            } else {
+                self.selected_encoder_index = self.encoders.len() - 1;
+            }
+            if self.encoder_problem(&self.encoders[self.selected_encoder_index]).is_none() {
+                break;
+            }
+        }
+        self.refresh_profiles();
+    }
+
+    // Offers the selected encoder's profiles, keeping the current one if it still applies
+    fn refresh_profiles(&mut self) {
+        let current = self.profile_list[self.selected_profile_index].clone();
+        self.profile_list = codecs::profile_choices(&self.encoders[self.selected_encoder_index])
+            .iter()
+            .map(|p| p.to_string())
+            .collect();
+        self.selected_profile_index = self.profile_list.iter().position(|p| *p == current).unwrap_or(0);
+    }
+
+    pub fn next_profile(&mut self) {
+        self.selected_profile_index = (self.selected_profile_index + 1) % self.profile_list.len();
+    }
+
+    pub fn previous_profile(&mut self) {
+        if self.selected_profile_index > 0 {
+            self.selected_profile_index -= 1;
+        } else {
+            self.selected_profile_index = self.profile_list.len() - 1;
+        }
+    }
+
+    pub fn next_retries(&mut self) {
+        self.selected_retries_index = (self.selected_retries_index + 1) % self.retries_list.len();
+    }
+
+    pub fn previous_retries(&mut self) {
+        if self.selected_retries_index > 0 {
+            self.selected_retries_index -= 1;
+        } else {
+            self.selected_retries_index = self.retries_list.len() - 1;
+        }
+    }
+
+    pub fn next_fallback(&mut self) {
+        self.selected_fallback_index = (self.selected_fallback_index + 1) % self.fallback_list.len();
+    }
+
+    pub fn previous_fallback(&mut self) {
+        if self.selected_fallback_index > 0 {
+            self.selected_fallback_index -= 1;
+        } else {
+            self.selected_fallback_index = self.fallback_list.len() - 1;
+        }
+    }
+
+    pub fn next_hw_decode(&mut self) {
+        self.selected_hw_decode_index = (self.selected_hw_decode_index + 1) % self.hw_decode_list.len();
+    }
+
+    pub fn previous_hw_decode(&mut self) {
+        if self.selected_hw_decode_index > 0 {
+            self.selected_hw_decode_index -= 1;
         } else {
-            self.selected_encoder_index = self.encoders.len() - 1;
+            self.selected_hw_decode_index = self.hw_decode_list.len() - 1;
         }
     }
 
@@ -216,7 +2167,155 @@ impl App {
         }
     }
 
+    pub fn next_pix_fmt(&mut self) {
+        self.selected_pix_fmt_index = (self.selected_pix_fmt_index + 1) % self.pix_fmt_list.len();
+    }
+
+    pub fn previous_pix_fmt(&mut self) {
+        if self.selected_pix_fmt_index > 0 {
+            self.selected_pix_fmt_index -= 1;
+        } else {
+            self.selected_pix_fmt_index = self.pix_fmt_list.len() - 1;
+        }
+    }
+
+    pub fn next_max_jobs(&mut self) {
+        self.selected_max_jobs_index = (self.selected_max_jobs_index + 1) % self.max_jobs_list.len();
+    }
+
+    pub fn previous_max_jobs(&mut self) {
+        if self.selected_max_jobs_index > 0 {
+            self.selected_max_jobs_index -= 1;
+        } else {
+            self.selected_max_jobs_index = self.max_jobs_list.len() - 1;
+        }
+    }
+
+    pub fn next_threads(&mut self) {
+        self.selected_threads_index = (self.selected_threads_index + 1) % self.threads_list.len();
+    }
+
+    pub fn previous_threads(&mut self) {
+        if self.selected_threads_index > 0 {
+            self.selected_threads_index -= 1;
+        } else {
+            self.selected_threads_index = self.threads_list.len() - 1;
+        }
+    }
+
+    pub fn next_hw_device(&mut self) {
+        self.selected_hw_device_index = (self.selected_hw_device_index + 1) % self.hw_device_list.len();
+    }
+
+    pub fn previous_hw_device(&mut self) {
+        if self.selected_hw_device_index > 0 {
+            self.selected_hw_device_index -= 1;
+        } else {
+            self.selected_hw_device_index = self.hw_device_list.len() - 1;
+        }
+    }
+
+    pub fn next_chunk_mode(&mut self) {
+        self.selected_chunk_mode_index = (self.selected_chunk_mode_index + 1) % self.chunk_mode_list.len();
+    }
+
+    pub fn previous_chunk_mode(&mut self) {
+        if self.selected_chunk_mode_index > 0 {
+            self.selected_chunk_mode_index -= 1;
+        } else {
+            self.selected_chunk_mode_index = self.chunk_mode_list.len() - 1;
+        }
+    }
+
+    pub fn next_verify_mode(&mut self) {
+        self.selected_verify_index = (self.selected_verify_index + 1) % self.verify_list.len();
+    }
+
+    pub fn previous_verify_mode(&mut self) {
+        if self.selected_verify_index > 0 {
+            self.selected_verify_index -= 1;
+        } else {
+            self.selected_verify_index = self.verify_list.len() - 1;
+        }
+    }
+
+    pub fn next_target_vmaf(&mut self) {
+        self.selected_target_vmaf_index = (self.selected_target_vmaf_index + 1) % self.target_vmaf_list.len();
+        self.forget_found_qps();
+    }
+
+    pub fn previous_target_vmaf(&mut self) {
+        if self.selected_target_vmaf_index > 0 {
+            self.selected_target_vmaf_index -= 1;
+        } else {
+            self.selected_target_vmaf_index = self.target_vmaf_list.len() - 1;
+        }
+        self.forget_found_qps();
+    }
+
+    // QPs found for the previous target; every entry searches again when it next runs
+    fn forget_found_qps(&mut self) {
+        for item in &mut self.queue {
+            item.target_qp = None;
+        }
+    }
+
+    pub fn next_metrics_mode(&mut self) {
+        self.selected_metrics_index = (self.selected_metrics_index + 1) % self.metrics_list.len();
+    }
+
+    pub fn previous_metrics_mode(&mut self) {
+        if self.selected_metrics_index > 0 {
+            self.selected_metrics_index -= 1;
+        } else {
+            self.selected_metrics_index = self.metrics_list.len() - 1;
+        }
+    }
+
+    pub fn next_output_template(&mut self) {
+        self.selected_output_template_index = (self.selected_output_template_index + 1) % self.output_template_list.len();
+        self.probe_resolutions();
+    }
+
+    pub fn previous_output_template(&mut self) {
+        if self.selected_output_template_index > 0 {
+            self.selected_output_template_index -= 1;
+        } else {
+            self.selected_output_template_index = self.output_template_list.len() - 1;
+        }
+        self.probe_resolutions();
+    }
+
+    pub fn next_overwrite_policy(&mut self) {
+        self.selected_overwrite_policy_index = (self.selected_overwrite_policy_index + 1) % self.overwrite_policy_list.len();
+    }
+
+    pub fn previous_overwrite_policy(&mut self) {
+        if self.selected_overwrite_policy_index > 0 {
+            self.selected_overwrite_policy_index -= 1;
+        } else {
+            self.selected_overwrite_policy_index = self.overwrite_policy_list.len() - 1;
+        }
+    }
+
     pub fn handle_key_event(&mut self, key: KeyEvent, tx: Sender<FfmpegEvent>) {
+        if self.text_prompt.is_some() {
+            self.handle_text_prompt_key(key);
+            return;
+        }
+        if self.overwrite_prompt.is_some() {
+            self.handle_overwrite_key(key, &tx);
+            return;
+        }
+        if self.browser.is_some() {
+            self.handle_browser_key(key);
+            return;
+        }
+        if self.log_viewer.is_some() {
+            self.handle_log_viewer_key(key);
+            return;
+        }
+
         match key.code {
             KeyCode::Char('q') | KeyCode::Esc => {
                 self.should_quit = true;
@@ -229,58 +2328,151 @@ impl App {
             }
             KeyCode::Left => {
                 match self.focus {
+                    Focus::Preset => self.previous_preset(),
                     Focus::Encoder => self.previous_encoder(),
+                    Focus::HwDecode => self.previous_hw_decode(),
                     Focus::Container => self.previous_container(),
                     Focus::Qp => self.previous_qp(),
+                    Focus::TargetVmaf => self.previous_target_vmaf(),
                     Focus::Fps => self.previous_fps(),
                     Focus::AudioBitrate => self.previous_audio_bitrate(),
+                    Focus::PixFmt => self.previous_pix_fmt(),
+                    Focus::Profile => self.previous_profile(),
+                    Focus::MaxJobs => self.previous_max_jobs(),
+                    Focus::Threads => self.previous_threads(),
+                    Focus::HwDevice => self.previous_hw_device(),
+                    Focus::ChunkMode => self.previous_chunk_mode(),
+                    Focus::Verify => self.previous_verify_mode(),
+                    Focus::OutputTemplate => self.previous_output_template(),
+                    Focus::Overwrite => self.previous_overwrite_policy(),
+                    Focus::Metrics => self.previous_metrics_mode(),
+                    Focus::Retries => self.previous_retries(),
+                    Focus::Fallback => self.previous_fallback(),
                     _ => {}
                 }
+                if self.focus.is_per_entry() {
+                    self.store_settings();
+                }
             }
             KeyCode::Right => {
                 match self.focus {
+                    Focus::Preset => self.next_preset(),
                     Focus::Encoder => self.next_encoder(),
+                    Focus::HwDecode => self.next_hw_decode(),
                     Focus::Container => self.next_container(),
                     Focus::Qp => self.next_qp(),
+                    Focus::TargetVmaf => self.next_target_vmaf(),
                     Focus::Fps => self.next_fps(),
                     Focus::AudioBitrate => self.next_audio_bitrate(),
+                    Focus::PixFmt => self.next_pix_fmt(),
+                    Focus::Profile => self.next_profile(),
+                    Focus::MaxJobs => self.next_max_jobs(),
+                    Focus::Threads => self.next_threads(),
+                    Focus::HwDevice => self.next_hw_device(),
+                    Focus::ChunkMode => self.next_chunk_mode(),
+                    Focus::Verify => self.next_verify_mode(),
+                    Focus::OutputTemplate => self.next_output_template(),
+                    Focus::Overwrite => self.next_overwrite_policy(),
+                    Focus::Metrics => self.next_metrics_mode(),
+                    Focus::Retries => self.next_retries(),
+                    Focus::Fallback => self.next_fallback(),
                     _ => {}
                 }
+                if self.focus.is_per_entry() {
+                    self.store_settings();
+                }
+            }
+            KeyCode::Char('[') => {
+                self.previous_queue_entry();
+            }
+            KeyCode::Char(']') => {
+                self.next_queue_entry();
             }
+            KeyCode::Char('o') => self.open_browser(),
+            KeyCode::Char('l') => self.open_log_viewer(),
+            KeyCode::Char('w') => self.save_defaults(),
+            KeyCode::Char('p') => self.toggle_command_preview(),
+            KeyCode::Char('y') if self.show_commands => self.copy_commands(),
+            KeyCode::Char('Y') if self.show_commands => self.export_commands(),
+            KeyCode::Char('n') if matches!(self.focus, Focus::Preset) => self.new_preset(),
+            KeyCode::Char('e') if matches!(self.focus, Focus::Preset) => self.rename_preset(),
+            KeyCode::Char('x') if matches!(self.focus, Focus::Preset) => self.delete_preset(),
+            KeyCode::Char('E') if matches!(self.focus, Focus::Preset) => self.export_preset(),
+            KeyCode::Delete => self.remove_selected(),
+            KeyCode::PageUp => self.move_selected_up(),
+            KeyCode::PageDown => self.move_selected_down(),
+            KeyCode::Char('d') => self.duplicate_selected(),
+            KeyCode::Char('s') => self.toggle_skip_selected(),
+            KeyCode::Char('c') => self.cancel_selected(),
+            KeyCode::Char('r') => self.retry_selected(&tx),
+            KeyCode::Char('R') => self.retry_failed(&tx),
+            KeyCode::Char('C') => self.clear_completed(),
             KeyCode::Enter => {
                 match self.focus {
                     Focus::Submit => {
                         if !self.is_encoding {
-                            if let Some((input, mut output)) = self.get_current_file() {
+                            if let Some(err) = self.queue_error() {
+                                self.log_messages.push(format!("ERROR: {}", err));
+                            } else if self.target_vmaf().is_some() && !metrics::vmaf_available() {
+                                self.log_messages.push("ERROR: Target VMAF needs an ffmpeg built with libvmaf".to_string());
+                            } else if self.get_next_file().is_some() {
                                 self.is_encoding = true;
-                                self.progress = 0.0;
-                                self.log_messages.clear();
-                                self.log_messages.push(format!("Starting encoding: {}", input));
-                                
-                                // Update output extension based on container
-                                let container = self.container_list[self.selected_container_index].clone();
-                                let path = std::path::Path::new(&output);
-                                output = path.with_extension(&container).to_string_lossy().to_string();
-
-                                let encoder = self.encoders[self.selected_encoder_index].clone();
-                                let qp = self.qp_list[self.selected_qp_index].clone();
-                                let bitrate = self.audio_bitrate_list[self.selected_audio_bitrate_index].clone();
-                                let fps = self.fps_list[self.selected_fps_index].clone();
-                                
-                                ffmpeg::start_encoding(input, output, encoder, qp, bitrate, fps, tx);
+                                let warnings: Vec<(usize, String)> = self
+                                    .queue
+                                    .iter()
+                                    .enumerate()
+                                    .filter(|(_, item)| item.status == JobStatus::Pending)
+                                    .filter_map(|(i, item)| {
+                                        let s = &item.settings;
+                                        codecs::playback_warning(&s.encoder, &s.container, &s.pix_fmt).map(|warning| (i, warning))
+                                    })
+                                    .collect();
+                                for (i, warning) in warnings {
+                                    self.log_entry(i, format!("WARNING: {}", warning));
+                                }
+                                self.start_pending_jobs(&tx);
                             } else {
                                 self.log_messages.push("No files in queue!".to_string());
                             }
                         }
                     }
+                    Focus::Preset => self.next_preset(),
                     Focus::Encoder => self.next_encoder(),
+                    Focus::HwDecode => self.next_hw_decode(),
                     Focus::Container => self.next_container(),
                     Focus::Qp => self.next_qp(),
+                    Focus::TargetVmaf => self.next_target_vmaf(),
                     Focus::Fps => self.next_fps(),
                     Focus::AudioBitrate => self.next_audio_bitrate(),
+                    Focus::PixFmt => self.next_pix_fmt(),
+                    Focus::Profile => self.next_profile(),
+                    Focus::InputArgs => self.edit_extra_args(PromptAction::InputArgs),
+                    Focus::OutputArgs => self.edit_extra_args(PromptAction::OutputArgs),
+                    Focus::MaxJobs => self.next_max_jobs(),
+                    Focus::Threads => self.next_threads(),
+                    Focus::HwDevice => self.next_hw_device(),
+                    Focus::ChunkMode => self.next_chunk_mode(),
+                    Focus::Verify => self.next_verify_mode(),
+                    Focus::OutputTemplate => self.next_output_template(),
+                    Focus::Overwrite => self.next_overwrite_policy(),
+                    Focus::Metrics => self.next_metrics_mode(),
+                    Focus::Retries => self.next_retries(),
+                    Focus::Fallback => self.next_fallback(),
+                }
+                if self.focus.is_per_entry() {
+                    self.store_settings();
                 }
             }
             _ => {}
         }
     }
 }
+
+// Points `index` at `value` in `list`, or records why it can't
+fn select_default(name: &str, value: Option<String>, list: &[String], index: &mut usize, errors: &mut Vec<String>) {
+    let Some(value) = value else { return };
+    match list.iter().position(|v| *v == value) {
+        Some(i) => *index = i,
+        None => errors.push(format!("{}: '{}' is not one of {}", name, value, list.join(", "))),
+    }
+}
@@ -0,0 +1,168 @@
+// File picker overlay for adding inputs from inside the TUI
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use crate::paths;
+use crate::probe;
+
+pub struct BrowserEntry {
+    pub path: PathBuf,
+    pub name: String,
+    pub is_dir: bool,
+    pub size: u64,
+    pub duration: Option<String>,
+}
+
+pub struct FileBrowser {
+    pub cwd: PathBuf,
+    pub entries: Vec<BrowserEntry>,
+    pub cursor: usize,
+    pub selected: HashSet<PathBuf>,
+    pub show_all_files: bool, // otherwise only video files are listed
+    pub error: Option<String>,
+    durations: Option<Receiver<(PathBuf, String)>>,
+}
+
+impl FileBrowser {
+    pub fn new(start: PathBuf, show_all_files: bool) -> Self {
+        let mut browser = Self {
+            cwd: start,
+            entries: Vec::new(),
+            cursor: 0,
+            selected: HashSet::new(),
+            show_all_files,
+            error: None,
+            durations: None,
+        };
+        browser.refresh();
+        browser
+    }
+
+    pub fn refresh(&mut self) {
+        self.entries.clear();
+        self.cursor = 0;
+        self.error = None;
+
+        let read_dir = match std::fs::read_dir(&self.cwd) {
+            Ok(r) => r,
+            Err(e) => {
+                self.error = Some(format!("Cannot read {}: {}", self.cwd.display(), e));
+                return;
+            }
+        };
+
+        for entry in read_dir.flatten() {
+            let path = entry.path();
+            let Ok(meta) = entry.metadata() else { continue };
+            let is_dir = meta.is_dir();
+            if !is_dir && !self.show_all_files && !paths::is_video_file(&path) {
+                continue;
+            }
+            self.entries.push(BrowserEntry {
+                name: entry.file_name().to_string_lossy().to_string(),
+                path,
+                is_dir,
+                size: meta.len(),
+                duration: None,
+            });
+        }
+
+        // Directories first, then files, each alphabetically
+        self.entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase())));
+
+        // Probing every file takes a while, so durations trickle in from a background thread
+        let files: Vec<PathBuf> = self
+            .entries
+            .iter()
+            .filter(|e| !e.is_dir && paths::is_video_file(&e.path))
+            .map(|e| e.path.clone())
+            .collect();
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for file in files {
+                let duration = probe::probe_duration(&file).map(probe::format_duration).unwrap_or_else(|| "?".to_string());
+                if tx.send((file, duration)).is_err() {
+                    break; // Browser moved on to another directory
+                }
+            }
+        });
+        self.durations = Some(rx);
+    }
+
+    pub fn poll_durations(&mut self) {
+        let Some(rx) = &self.durations else { return };
+        while let Ok((path, duration)) = rx.try_recv() {
+            if let Some(entry) = self.entries.iter_mut().find(|e| e.path == path) {
+                entry.duration = Some(duration);
+            }
+        }
+    }
+
+    pub fn cursor_up(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    pub fn cursor_down(&mut self) {
+        if self.cursor + 1 < self.entries.len() {
+            self.cursor += 1;
+        }
+    }
+
+    pub fn current(&self) -> Option<&BrowserEntry> {
+        self.entries.get(self.cursor)
+    }
+
+    pub fn enter_directory(&mut self, dir: &Path) {
+        self.cwd = dir.to_path_buf();
+        self.refresh();
+    }
+
+    pub fn go_up(&mut self) {
+        let Some(parent) = self.cwd.parent().map(Path::to_path_buf) else { return };
+        let previous = self.cwd.clone();
+        self.enter_directory(&parent);
+        // Keep the cursor on the directory we just left
+        if let Some(i) = self.entries.iter().position(|e| e.path == previous) {
+            self.cursor = i;
+        }
+    }
+
+    pub fn toggle_selected(&mut self) {
+        let Some(entry) = self.current() else { return };
+        if entry.is_dir {
+            return;
+        }
+        let path = entry.path.clone();
+        if !self.selected.remove(&path) {
+            self.selected.insert(path);
+        }
+        self.cursor_down();
+    }
+
+    pub fn select_all(&mut self) {
+        let files: Vec<PathBuf> = self.entries.iter().filter(|e| !e.is_dir).map(|e| e.path.clone()).collect();
+        if files.iter().all(|f| self.selected.contains(f)) {
+            for f in &files {
+                self.selected.remove(f);
+            }
+        } else {
+            self.selected.extend(files);
+        }
+    }
+
+    pub fn toggle_show_all(&mut self) {
+        self.show_all_files = !self.show_all_files;
+        self.refresh();
+    }
+
+    // Files to queue: the multi-selection, or the file under the cursor if nothing is selected
+    pub fn take_selection(&mut self) -> Vec<PathBuf> {
+        if self.selected.is_empty() {
+            return self.current().filter(|e| !e.is_dir).map(|e| vec![e.path.clone()]).unwrap_or_default();
+        }
+        let mut files: Vec<PathBuf> = self.selected.drain().collect();
+        files.sort();
+        files
+    }
+}
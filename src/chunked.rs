@@ -0,0 +1,467 @@
+// Chunked encoding: split one long input at keyframes (optionally only those
+// at scene cuts), encode the chunks in parallel and join them with the concat
+// demuxer. Audio is encoded once from the original input during the join.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use regex::Regex;
+use crate::extra_args;
+use crate::ffmpeg::{self, EncodeSettings, FfmpegEvent};
+
+pub const CHUNK_MODE_OFF: &str = "Off";
+pub const CHUNK_MODE_KEYFRAMES: &str = "Keyframes";
+pub const CHUNK_MODE_SCENES: &str = "Scenes";
+
+// Scene change score (0-1) above which ffmpeg's `scene` metric counts as a cut
+const SCENE_THRESHOLD: f64 = 0.4;
+// A keyframe this close (in seconds) to a detected cut is used as a boundary
+const SCENE_SNAP_SECONDS: f64 = 0.5;
+// Never make chunks shorter than this, so encoder lookahead still has room
+const MIN_CHUNK_SECONDS: f64 = 2.0;
+
+pub fn chunk_mode_choices() -> Vec<String> {
+    vec![
+        CHUNK_MODE_OFF.to_string(),
+        CHUNK_MODE_KEYFRAMES.to_string(),
+        CHUNK_MODE_SCENES.to_string(),
+    ]
+}
+
+// Video frames of the source in presentation order
+pub struct FrameIndex {
+    pub times: Vec<f64>,      // pts of every frame, in seconds
+    pub keyframes: Vec<usize>, // indices into `times`
+    pub start_time: f64,       // container start time that `-ss` is relative to
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chunk {
+    pub start_frame: usize,
+    pub frames: usize,
+}
+
+pub fn frame_index_command(input: &str) -> Command {
+    let mut cmd = ffmpeg::ffprobe_command();
+    cmd.args(["-v", "error", "-select_streams", "v:0", "-show_entries", "packet=pts_time,flags", "-of", "csv=p=0"])
+        .arg(input);
+    cmd
+}
+
+pub fn probe_frames(input: &str, cancel: &AtomicBool) -> Result<FrameIndex, String> {
+    let (status, stdout, stderr) = ffmpeg::run_cancellable_output(frame_index_command(input), cancel)?;
+    if !status.success() {
+        return Err(format!("ffprobe failed: {}", stderr.trim()));
+    }
+
+    let mut packets: Vec<(f64, bool)> = stdout
+        .lines()
+        .filter_map(|line| {
+            let (pts, flags) = line.split_once(',')?;
+            Some((pts.trim().parse().ok()?, flags.contains('K')))
+        })
+        .collect();
+    if packets.is_empty() {
+        return Err("Input has no video frames".to_string());
+    }
+
+    // Packets come in decode order; B-frames make that differ from display order
+    packets.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut start = ffmpeg::ffprobe_command();
+    start.args(["-v", "error", "-show_entries", "format=start_time", "-of", "csv=p=0"]).arg(input);
+    let (_, start, _) = ffmpeg::run_cancellable_output(start, cancel)?;
+    let start_time = start.trim().parse().unwrap_or(packets[0].0);
+
+    Ok(FrameIndex {
+        times: packets.iter().map(|p| p.0).collect(),
+        keyframes: packets.iter().enumerate().filter(|(_, p)| p.1).map(|(i, _)| i).collect(),
+        start_time,
+    })
+}
+
+pub fn scene_detect_command(input: &str) -> Command {
+    let mut cmd = ffmpeg::ffmpeg_command();
+    cmd.args(["-hide_banner", "-nostdin", "-nostats", "-i"])
+        .arg(input)
+        .args(["-map", "0:v:0", "-an", "-vf"])
+        .arg(format!("select='gt(scene,{})',showinfo", SCENE_THRESHOLD))
+        .args(["-f", "null", "-"]);
+    cmd
+}
+
+// Decodes the whole input, so it is killed as soon as the job is cancelled
+pub fn detect_scene_cuts(input: &str, cancel: &AtomicBool) -> Result<Vec<f64>, String> {
+    let (status, _, stderr) = ffmpeg::run_cancellable_output(scene_detect_command(input), cancel)?;
+    if !status.success() {
+        return Err("Scene detection failed".to_string());
+    }
+
+    let re_pts = Regex::new(r"pts_time:\s*([\d.]+)").unwrap();
+    Ok(stderr
+        .lines()
+        .filter(|line| line.contains("Parsed_showinfo"))
+        .filter_map(|line| re_pts.captures(line)?[1].parse().ok())
+        .collect())
+}
+
+// Splits the frame list at keyframes. With `cuts`, only keyframes near a scene
+// cut are boundaries and chunks may be as short as `min_frames`; otherwise
+// chunks are grown to roughly `target_frames`.
+pub fn plan_chunks(index: &FrameIndex, cuts: Option<&[f64]>, target_frames: usize, min_frames: usize) -> Vec<Chunk> {
+    let total = index.times.len();
+    let mut boundaries = vec![0];
+
+    for &k in &index.keyframes {
+        let current = *boundaries.last().unwrap();
+        if k == 0 || k <= current {
+            continue;
+        }
+        let length = k - current;
+        let is_boundary = match cuts {
+            Some(cuts) => {
+                let t = index.times[k];
+                length >= min_frames && cuts.iter().any(|c| (c - t).abs() <= SCENE_SNAP_SECONDS)
+            }
+            None => length >= target_frames,
+        };
+        // Don't leave a runt chunk at the very end
+        if is_boundary && total - k >= min_frames {
+            boundaries.push(k);
+        }
+    }
+    boundaries.push(total);
+
+    boundaries
+        .windows(2)
+        .map(|w| Chunk { start_frame: w[0], frames: w[1] - w[0] })
+        .collect()
+}
+
+// Where `-ss` starts the chunk beginning at `frame`. Half a frame early, so the
+// boundary keyframe itself is kept.
+pub fn seek_time(index: &FrameIndex, frame: usize, frame_interval: f64) -> f64 {
+    (index.times[frame] - index.start_time - frame_interval / 2.0).max(0.0)
+}
+
+// Counts video packets (== frames) in an encoded file
+pub fn count_frames(path: &Path, cancel: &AtomicBool) -> Result<usize, String> {
+    let mut cmd = ffmpeg::ffprobe_command();
+    cmd.args(["-v", "error", "-select_streams", "v:0", "-count_packets", "-show_entries", "stream=nb_read_packets", "-of", "csv=p=0"])
+        .arg(path);
+    let (_, stdout, _) = ffmpeg::run_cancellable_output(cmd, cancel)?;
+    stdout
+        .trim()
+        .parse()
+        .map_err(|_| format!("Could not count frames in {}", path.display()))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn start_chunked_encoding(
+    job: usize,
+    input: String,
+    output: String,
+    settings: EncodeSettings,
+    mode: String,
+    workers: usize,
+    threads: Option<String>,
+    cancel: Arc<AtomicBool>,
+    tx: Sender<FfmpegEvent>,
+) {
+    thread::spawn(move || {
+        let settings = ffmpeg::with_working_decoder(job, &input, &settings, &cancel, &tx);
+        let work_dir = chunk_dir(&output);
+        let result = encode_in_chunks(job, &input, &output, &settings, &mode, workers, &threads, &cancel, &work_dir, &tx);
+        let _ = std::fs::remove_dir_all(&work_dir);
+
+        match result {
+            Ok(()) => tx.send(FfmpegEvent::Done(job)).unwrap(),
+            Err(e) => tx.send(FfmpegEvent::Error(job, e)).unwrap(),
+        }
+    });
+}
+
+pub fn chunk_dir(output: &str) -> PathBuf {
+    let path = Path::new(output);
+    let parent = path.parent().unwrap_or_else(|| Path::new("."));
+    // Outputs are hidden temp files already; don't stack the dots
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    parent.join(format!(".{}_chunks", stem.trim_start_matches('.')))
+}
+
+// Encodes `frames` frames starting at `seek` seconds, video only
+pub fn chunk_command(
+    input: &str,
+    settings: &EncodeSettings,
+    threads: &Option<String>,
+    seek: &str,
+    frames: &str,
+    chunk_path: &Path,
+) -> Result<Command, String> {
+    let (input_args, output_args) = ffmpeg::encode_args(settings, threads)?;
+
+    let mut cmd = ffmpeg::ffmpeg_command();
+    cmd.args(["-y", "-nostdin"])
+        .args(&input_args)
+        .args(["-ss", seek, "-i", input])
+        .args(["-map", "0:v:0", "-an", "-frames:v", frames])
+        .args(&output_args)
+        .arg(chunk_path);
+    Ok(cmd)
+}
+
+// Joins the chunks without re-encoding and adds the source's audio, keeping the
+// one audio stream a whole-file encode would (verify expects the same layout)
+pub fn concat_command(list_path: &Path, input: &str, output: &str, audio_bitrate: &str) -> Command {
+    let mut cmd = ffmpeg::ffmpeg_command();
+    cmd.args([ffmpeg::overwrite_flag(output), "-nostdin", "-f", "concat", "-safe", "0", "-i"])
+        .arg(list_path)
+        .arg("-i")
+        .arg(input)
+        .args(["-map", "0:v:0", "-map", "1:a:0?", "-c:v", "copy", "-c:a", "aac", "-b:a", audio_bitrate])
+        .arg(output);
+    cmd
+}
+
+#[allow(clippy::too_many_arguments)]
+fn encode_in_chunks(
+    job: usize,
+    input: &str,
+    output: &str,
+    settings: &EncodeSettings,
+    mode: &str,
+    workers: usize,
+    threads: &Option<String>,
+    cancel: &AtomicBool,
+    work_dir: &Path,
+    tx: &Sender<FfmpegEvent>,
+) -> Result<(), String> {
+    // Frame-exact chunks rely on the output having the same frames as the input
+    if settings.fps != "Same" {
+        return Err("Chunked encoding requires FPS \"Same\"".to_string());
+    }
+    let (input_args, output_args) = extra_args::for_settings(settings)?;
+    extra_args::check_untrimmed(&input_args, "chunked encoding")?;
+    extra_args::check_untrimmed(&output_args, "chunked encoding")?;
+
+    // Each step below can take minutes on a long input; stop between them too
+    let check_cancel = || if cancel.load(Ordering::SeqCst) { Err("Cancelled".to_string()) } else { Ok(()) };
+
+    check_cancel()?;
+    let _ = tx.send(FfmpegEvent::Log(job, "Indexing frames...".to_string()));
+    let index = probe_frames(input, cancel)?;
+    let total = index.times.len();
+    let duration = (index.times[total - 1] - index.times[0]).max(0.001);
+    let frame_interval = duration / total as f64;
+
+    let cuts = if mode == CHUNK_MODE_SCENES {
+        check_cancel()?;
+        let _ = tx.send(FfmpegEvent::Log(job, "Detecting scene cuts...".to_string()));
+        let cuts = detect_scene_cuts(input, cancel)?;
+        let _ = tx.send(FfmpegEvent::Log(job, format!("Found {} scene cuts", cuts.len())));
+        Some(cuts)
+    } else {
+        None
+    };
+
+    let min_frames = (MIN_CHUNK_SECONDS / frame_interval).ceil() as usize;
+    let target_frames = (total / (workers * 4).max(1)).max(min_frames);
+    let chunks = plan_chunks(&index, cuts.as_deref().filter(|c| !c.is_empty()), target_frames, min_frames);
+    let _ = tx.send(FfmpegEvent::Log(job, format!("Split {} frames into {} chunks", total, chunks.len())));
+
+    std::fs::create_dir_all(work_dir).map_err(|e| format!("Cannot create {}: {}", work_dir.display(), e))?;
+
+    let next_chunk = AtomicUsize::new(0);
+    let chunks_done = AtomicUsize::new(0);
+    let frames_done: Vec<AtomicUsize> = chunks.iter().map(|_| AtomicUsize::new(0)).collect();
+    let failed = AtomicBool::new(false);
+    let failure: Mutex<Option<String>> = Mutex::new(None);
+    let re_frame = Regex::new(r"frame=\s*(\d+)").unwrap();
+
+    thread::scope(|scope| {
+        for _ in 0..workers.max(1) {
+            scope.spawn(|| {
+                loop {
+                    let i = next_chunk.fetch_add(1, Ordering::SeqCst);
+                    if i >= chunks.len() || failed.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    if cancel.load(Ordering::SeqCst) {
+                        failed.store(true, Ordering::SeqCst);
+                        failure.lock().unwrap().get_or_insert("Cancelled".to_string());
+                        break;
+                    }
+                    let chunk = &chunks[i];
+                    let chunk_path = work_dir.join(format!("chunk_{:05}.mkv", i));
+
+                    let seek = seek_time(&index, chunk.start_frame, frame_interval);
+
+                    let mut cmd = match chunk_command(input, settings, threads, &format!("{:.6}", seek), &chunk.frames.to_string(), &chunk_path) {
+                        Ok(cmd) => cmd,
+                        Err(e) => {
+                            failed.store(true, Ordering::SeqCst);
+                            failure.lock().unwrap().get_or_insert(e);
+                            break;
+                        }
+                    };
+                    cmd.stdin(Stdio::null()).stderr(Stdio::piped());
+
+                    let result = cmd.spawn().map_err(|e| format!("Failed to start ffmpeg: {}", e)).and_then(|mut child| {
+                        let stderr = child.stderr.take().unwrap();
+                        let mut last_message = String::new();
+                        let _ = ffmpeg::read_lines(stderr, |line| {
+                            if cancel.load(Ordering::SeqCst) {
+                                let _ = child.kill();
+                            }
+                            if !line.starts_with("frame=") {
+                                last_message = line.trim().to_string();
+                                let _ = tx.send(FfmpegEvent::Output(job, format!("chunk {}: {}", i + 1, line)));
+                            }
+                            if let Some(caps) = re_frame.captures(&line) {
+                                frames_done[i].store(caps[1].parse().unwrap_or(0), Ordering::SeqCst);
+                                let done: usize = frames_done.iter().map(|f| f.load(Ordering::SeqCst)).sum();
+                                let _ = tx.send(FfmpegEvent::Progress(
+                                    job,
+                                    (done as f64 / total as f64).min(1.0),
+                                    "-".to_string(),
+                                    "-".to_string(),
+                                    "-".to_string(),
+                                    format!("{}/{} chunks", chunks_done.load(Ordering::SeqCst), chunks.len()),
+                                ));
+                            }
+                        });
+                        match child.wait() {
+                            _ if cancel.load(Ordering::SeqCst) => Err("Cancelled".to_string()),
+                            Ok(status) if status.success() => Ok(()),
+                            _ => Err(format!("Chunk {} failed to encode: {}", i + 1, last_message)),
+                        }
+                    });
+
+                    // A boundary off by even one frame would duplicate or drop frames after the join
+                    let result = result.and_then(|_| {
+                        let encoded = count_frames(&chunk_path, cancel)?;
+                        if encoded == chunk.frames {
+                            Ok(())
+                        } else {
+                            Err(format!("Chunk {} has {} frames, expected {}", i + 1, encoded, chunk.frames))
+                        }
+                    });
+
+                    match result {
+                        Ok(()) => {
+                            chunks_done.fetch_add(1, Ordering::SeqCst);
+                        }
+                        Err(e) => {
+                            failed.store(true, Ordering::SeqCst);
+                            failure.lock().unwrap().get_or_insert(e);
+                        }
+                    }
+                }
+            });
+        }
+    });
+
+    if let Some(e) = failure.into_inner().unwrap() {
+        return Err(e);
+    }
+
+    check_cancel()?;
+    let _ = tx.send(FfmpegEvent::Log(job, "Joining chunks...".to_string()));
+    let list_path = work_dir.join("chunks.txt");
+    let list: String = (0..chunks.len()).map(|i| format!("file 'chunk_{:05}.mkv'\n", i)).collect();
+    std::fs::write(&list_path, list).map_err(|e| format!("Cannot write {}: {}", list_path.display(), e))?;
+
+    let mut child = concat_command(&list_path, input, output, &settings.audio_bitrate)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to start ffmpeg: {}", e))?;
+    let stderr = child.stderr.take().unwrap();
+    let mut last_message = String::new();
+    let _ = ffmpeg::read_lines(stderr, |line| {
+        if cancel.load(Ordering::SeqCst) {
+            let _ = child.kill();
+        }
+        if !line.starts_with("frame=") && !line.starts_with("size=") {
+            last_message = line.trim().to_string();
+            let _ = tx.send(FfmpegEvent::Output(job, format!("join: {}", line)));
+        }
+    });
+    match child.wait() {
+        _ if cancel.load(Ordering::SeqCst) => return Err("Cancelled".to_string()),
+        Ok(status) if status.success() => {}
+        _ => return Err(format!("Joining chunks failed: {}", last_message)),
+    }
+
+    let joined = count_frames(Path::new(output), cancel)?;
+    if joined != total {
+        return Err(format!("Joined output has {} frames, source has {}", joined, total));
+    }
+    let _ = tx.send(FfmpegEvent::Log(job, format!("Verified {} frames across {} chunks", joined, chunks.len())));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 100 frames at 25 fps starting at `start`, a keyframe every `gop` frames
+    fn index(gop: usize, start: f64) -> FrameIndex {
+        FrameIndex {
+            times: (0..100).map(|i| start + i as f64 * 0.04).collect(),
+            keyframes: (0..100).step_by(gop).collect(),
+            start_time: start,
+        }
+    }
+
+    fn starts(chunks: &[Chunk]) -> Vec<(usize, usize)> {
+        chunks.iter().map(|c| (c.start_frame, c.frames)).collect()
+    }
+
+    #[test]
+    fn keyframe_chunks_grow_to_the_target() {
+        let chunks = plan_chunks(&index(10, 0.0), None, 25, 5);
+        assert_eq!(starts(&chunks), [(0, 30), (30, 30), (60, 30), (90, 10)]);
+        assert_eq!(chunks.iter().map(|c| c.frames).sum::<usize>(), 100);
+    }
+
+    #[test]
+    fn no_runt_chunk_at_the_end() {
+        let chunks = plan_chunks(&index(10, 0.0), None, 25, 15);
+        assert_eq!(starts(&chunks), [(0, 30), (30, 30), (60, 40)]);
+    }
+
+    #[test]
+    fn scene_cuts_snap_to_nearby_keyframes() {
+        // Keyframes at 0, 1, 2 and 3 seconds; only the one at 2s is near a cut
+        let chunks = plan_chunks(&index(25, 0.0), Some(&[2.1]), 1000, 5);
+        assert_eq!(starts(&chunks), [(0, 50), (50, 50)]);
+        // A cut past the snap distance is no boundary
+        assert_eq!(starts(&plan_chunks(&index(50, 0.0), Some(&[1.2]), 1000, 5)), [(0, 100)]);
+    }
+
+    #[test]
+    fn scene_chunks_keep_the_minimum_length() {
+        let chunks = plan_chunks(&index(25, 0.0), Some(&[0.95, 2.05]), 1000, 30);
+        assert_eq!(starts(&chunks), [(0, 50), (50, 50)]);
+    }
+
+    #[test]
+    fn repeated_or_earlier_keyframes_are_skipped() {
+        let index = FrameIndex { keyframes: vec![0, 50, 50, 30, 75], ..index(10, 0.0) };
+        assert_eq!(starts(&plan_chunks(&index, None, 10, 5)), [(0, 50), (50, 25), (75, 25)]);
+    }
+
+    #[test]
+    fn seeks_half_a_frame_before_the_boundary() {
+        let index = index(10, 1.4);
+        assert!((seek_time(&index, 30, 0.04) - 1.18).abs() < 1e-9);
+        // Never before the start of the file
+        assert_eq!(seek_time(&index, 0, 0.04), 0.0);
+    }
+}
@@ -0,0 +1,140 @@
+// Encoder knowledge: which pixel formats, profiles and containers each encoder
+// accepts and which encoder/container/pixel format combinations are known to play badly.
+
+pub const PIX_FMT_AUTO: &str = "Auto";
+pub const PIX_FMT_SAME: &str = "Same";
+
+// The only profile offered for encoders without profile choices
+pub const PROFILE_DEFAULT: &str = "Default";
+
+// Profiles offered in the UI; the first one is the default
+pub fn profile_choices(encoder: &str) -> &'static [&'static str] {
+    match encoder {
+        "prores_ks" => &["hq", "standard", "lt", "proxy", "4444", "4444xq"],
+        // DNxHR is resolution independent; classic DNxHD needs fixed bitrates per format
+        "dnxhd" => &["dnxhr_hq", "dnxhr_sq", "dnxhr_lb", "dnxhr_hqx", "dnxhr_444"],
+        _ => &[PROFILE_DEFAULT],
+    }
+}
+
+// Intra-frame and lossless encoders whose quality comes from the profile, not a QP
+pub fn uses_qp(encoder: &str) -> bool {
+    !matches!(encoder, "prores_ks" | "dnxhd" | "ffv1")
+}
+
+// Containers the encoder's output can be muxed into at all; None means no restriction
+pub fn supported_containers(encoder: &str) -> Option<&'static [&'static str]> {
+    match encoder {
+        "prores_ks" | "dnxhd" => Some(&["mov", "mkv"]),
+        "ffv1" => Some(&["mkv", "avi"]),
+        "libx264rgb" => Some(&["mkv", "mp4", "mov"]),
+        _ => None,
+    }
+}
+
+pub fn container_error(encoder: &str, container: &str) -> Option<String> {
+    let allowed = supported_containers(encoder)?;
+    if allowed.contains(&container) {
+        None
+    } else {
+        Some(format!("{} can't be written to {}, use {}", encoder, container.to_uppercase(), allowed.join(" or ").to_uppercase()))
+    }
+}
+
+pub fn pix_fmt_choices() -> Vec<String> {
+    vec![
+        PIX_FMT_AUTO.to_string(),
+        "yuv420p10le".to_string(),
+        "yuv444p".to_string(),
+        PIX_FMT_SAME.to_string(),
+    ]
+}
+
+// Pixel formats (as offered in the UI) that an encoder can produce.
+pub fn supported_pix_fmts(encoder: &str) -> &'static [&'static str] {
+    match encoder {
+        "libx264" | "libx265" | "libvpx-vp9" | "libaom-av1" | "librav1e" => &["yuv420p", "yuv420p10le", "yuv444p"],
+        "libsvtav1" => &["yuv420p", "yuv420p10le"],
+        "prores_ks" => &["yuv422p10le", "yuv444p10le"],
+        "dnxhd" => &["yuv422p", "yuv422p10le", "yuv444p10le"],
+        "ffv1" => &["yuv420p", "yuv420p10le", "yuv444p", "yuv422p10le", "yuv444p10le"],
+        "libx264rgb" => &["rgb24"],
+        "h264_nvenc" => &["yuv420p", "yuv444p"],
+        "hevc_nvenc" => &["yuv420p", "yuv420p10le", "yuv444p"],
+        "av1_nvenc" => &["yuv420p", "yuv420p10le"],
+        // Uploaded as NV12/P010, see hwaccel::upload_format
+        "h264_vaapi" | "h264_qsv" => &["yuv420p"],
+        "hevc_vaapi" | "hevc_qsv" | "av1_vaapi" | "av1_qsv" => &["yuv420p", "yuv420p10le"],
+        _ => &["yuv420p"],
+    }
+}
+
+// What "Auto" means for an encoder: the widely playable yuv420p, or what the
+// format is defined for. None keeps the source format (lossless archiving).
+fn auto_pix_fmt(encoder: &str, profile: &str) -> Option<&'static str> {
+    match (encoder, profile) {
+        ("prores_ks", "4444" | "4444xq") | ("dnxhd", "dnxhr_444") => Some("yuv444p10le"),
+        ("prores_ks", _) | ("dnxhd", "dnxhr_hqx") => Some("yuv422p10le"),
+        ("dnxhd", _) => Some("yuv422p"),
+        ("libx264rgb", _) => Some("rgb24"),
+        ("ffv1", _) => None,
+        _ => Some("yuv420p"),
+    }
+}
+
+// Maps the UI choice to the value passed to `-pix_fmt`.
+// `Ok(None)` means the flag is omitted and the source format is kept.
+pub fn resolve_pix_fmt(encoder: &str, profile: &str, choice: &str) -> Result<Option<String>, String> {
+    let wanted = match choice {
+        PIX_FMT_SAME => return Ok(None),
+        PIX_FMT_AUTO => match auto_pix_fmt(encoder, profile) {
+            Some(pix_fmt) => pix_fmt,
+            None => return Ok(None),
+        },
+        other => other,
+    };
+
+    if !supported_pix_fmts(encoder).contains(&wanted) {
+        return Err(format!("{} does not support pixel format {}", encoder, wanted));
+    }
+
+    // NVENC takes 10-bit input as P010 rather than planar yuv420p10le
+    if encoder.contains("nvenc") && wanted == "yuv420p10le" {
+        return Ok(Some("p010le".to_string()));
+    }
+
+    Ok(Some(wanted.to_string()))
+}
+
+// AV1 quantizer index (0-255) covering the same range as a 0-51 QP
+pub fn av1_qindex(qp: u32) -> u32 {
+    ((qp * 255 + 25) / 51).min(255)
+}
+
+// Returns a short warning when the combination is valid for ffmpeg but
+// unlikely to play in common players, browsers or hardware decoders.
+pub fn playback_warning(encoder: &str, container: &str, pix_fmt: &str) -> Option<String> {
+    let is_h264 = encoder == "libx264" || encoder.starts_with("h264_");
+    let is_hevc = encoder == "libx265" || encoder.starts_with("hevc_");
+    let is_vp9_or_av1 = encoder == "libvpx-vp9" || encoder.contains("av1");
+
+    if container == "webm" && !is_vp9_or_av1 {
+        return Some(format!("WEBM only supports VP9/AV1, not {}", encoder));
+    }
+    if container == "gif" {
+        return Some("GIF output ignores the codec and produces a large palette file".to_string());
+    }
+    if container == "avi" && (is_hevc || is_vp9_or_av1) {
+        return Some(format!("{} in AVI is not supported by most players", encoder));
+    }
+    if container == "mov" && is_vp9_or_av1 {
+        return Some(format!("{} in MOV plays only in a few players", encoder));
+    }
+
+    match pix_fmt {
+        "yuv444p" => Some("4:4:4 output is not decoded by most browsers and hardware decoders".to_string()),
+        "yuv420p10le" if is_h264 => Some("10-bit H.264 (High 10) is rarely supported by players".to_string()),
+        PIX_FMT_SAME => Some("Keeping the source format may produce 4:2:2/4:4:4 output".to_string()),
+        _ => None,
+    }
+}
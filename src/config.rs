@@ -0,0 +1,127 @@
+// User configuration, read from config.toml in the platform config directory
+// (~/.config/videoencoder on Linux). Every key is optional; anything missing
+// keeps the built-in default.
+use std::path::{Path, PathBuf};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize};
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub defaults: Defaults,
+    pub encoders: Option<Vec<String>>,   // replaces the built-in encoder list
+    pub containers: Option<Vec<String>>, // replaces the built-in container list
+    pub output_template: Option<String>,
+    pub ffmpeg_path: Option<String>,
+    pub ffprobe_path: Option<String>,
+    pub log_dir: Option<String>, // where job logs are written; "" turns them off
+    pub ui: UiOptions,
+}
+
+// Initial values of the settings grid; each must be one of the values offered there
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Defaults {
+    pub encoder: Option<String>,
+    pub hw_decode: Option<String>,
+    pub container: Option<String>,
+    #[serde(deserialize_with = "string_or_number")]
+    pub qp: Option<String>,
+    #[serde(deserialize_with = "string_or_number")]
+    pub fps: Option<String>,
+    pub audio_bitrate: Option<String>,
+    pub pix_fmt: Option<String>,
+    pub profile: Option<String>,
+    pub input_args: Option<String>,  // extra ffmpeg options before the input
+    pub output_args: Option<String>, // extra ffmpeg options before the output
+    #[serde(deserialize_with = "string_or_number")]
+    pub max_jobs: Option<String>,
+    #[serde(deserialize_with = "string_or_number")]
+    pub threads: Option<String>,
+    pub hw_device: Option<String>, // render node for VAAPI/QSV, e.g. "/dev/dri/renderD128"
+    pub chunk_mode: Option<String>,
+    pub verify: Option<String>,
+    pub metrics: Option<String>,
+    #[serde(deserialize_with = "string_or_number")]
+    pub retries: Option<String>,
+    pub fallback: Option<String>,
+    #[serde(deserialize_with = "string_or_number")]
+    pub target_vmaf: Option<String>,
+    pub overwrite: Option<String>,
+}
+
+// Numbers may be written bare (qp = 23) or quoted (qp = "23")
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TextOrNumber {
+    Text(String),
+    Integer(i64),
+}
+
+impl From<TextOrNumber> for String {
+    fn from(value: TextOrNumber) -> Self {
+        match value {
+            TextOrNumber::Text(text) => text,
+            TextOrNumber::Integer(n) => n.to_string(),
+        }
+    }
+}
+
+pub fn string_or_number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    Ok(Option::<TextOrNumber>::deserialize(deserializer)?.map(String::from))
+}
+
+pub fn required_string_or_number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    Ok(TextOrNumber::deserialize(deserializer)?.into())
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UiOptions {
+    pub log_lines: Option<usize>,     // log lines shown on the dashboard
+    pub show_all_files: Option<bool>, // file browser lists non-video files too
+}
+
+// Where config.toml and presets.toml live
+pub fn config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("videoencoder"))
+}
+
+pub fn config_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("config.toml"))
+}
+
+// A missing file is not an error; it just means built-in defaults
+pub fn load() -> Result<Config, String> {
+    let Some(path) = config_path() else {
+        return Ok(Config::default());
+    };
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+        Err(e) => return Err(format!("Cannot read {}: {}", path.display(), e)),
+    };
+    parse_toml(&path, &text)
+}
+
+// Parse errors as one line for the log: "config.toml:4: unknown field `qpp`, expected ..."
+pub fn parse_toml<T: DeserializeOwned>(path: &Path, text: &str) -> Result<T, String> {
+    toml::from_str(text).map_err(|e| {
+        let line = e.span().map(|span| text[..span.start].matches('\n').count() + 1);
+        match line {
+            Some(line) => format!("{}:{}: {}", path.display(), line, e.message()),
+            None => format!("{}: {}", path.display(), e.message()),
+        }
+    })
+}
+
+pub fn save(config: &Config) -> Result<PathBuf, String> {
+    let path = config_path().ok_or("No config directory on this system")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+    }
+    let text = toml::to_string_pretty(config).map_err(|e| format!("Cannot serialize config: {}", e))?;
+    std::fs::write(&path, text).map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+    Ok(path)
+}
+
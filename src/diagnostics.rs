@@ -0,0 +1,174 @@
+// Explains why a job failed. ffmpeg's own message is usually buried among stream
+// info and progress lines, so its messages in the job's output are searched for the
+// errors people run into most, each with what to change in the settings.
+use std::sync::LazyLock;
+use regex::Regex;
+use crate::ffmpeg;
+use crate::hwcaps;
+
+#[derive(Clone)]
+pub struct Diagnosis {
+    pub problem: String,
+    pub fix: String,
+}
+
+impl Diagnosis {
+    fn new(problem: impl Into<String>, fix: &str) -> Self {
+        Self { problem: problem.into(), fix: fix.to_string() }
+    }
+}
+
+// A known cause: any of its words in ffmpeg's messages, what it means and what to change
+type Check = (&'static [&'static str], &'static str, &'static str);
+
+// Problems with the machine come first; they cause the codec errors further down
+const MACHINE_CHECKS: &[Check] = &[
+    (&["No space left on device", "ENOSPC"], "The disk is full", "Free up space, or write the output to another drive with OUTPUT NAME"),
+    (
+        &["Permission denied", "Operation not permitted"],
+        "A file could not be opened: permission denied",
+        "Check that the output folder is writable and the input readable, or pick another OUTPUT NAME",
+    ),
+];
+
+const SETTINGS_CHECKS: &[Check] = &[
+    (
+        &["Encoder not found"],
+        "This ffmpeg lacks the selected encoder",
+        "Pick another CODEC, or install an ffmpeg build that includes it (see ffmpeg -encoders)",
+    ),
+    (
+        &["codec not currently supported in container", "Could not find tag for codec", "not supported by the muxer"],
+        "The output FORMAT can't hold this codec",
+        "Pick a FORMAT that supports the codec; MKV accepts almost all of them",
+    ),
+    (
+        &["Specified pixel format", "Incompatible pixel format", "Invalid pixel format", "No such pixel format", "Unsupported pixel format"],
+        "The encoder can't produce the selected pixel format",
+        "Set PIXEL FORMAT to Auto, or pick a format the CODEC supports",
+    ),
+    (
+        &["No such file or directory"],
+        "A file or folder doesn't exist",
+        "Check that the input is still there and the output folder is reachable",
+    ),
+    (
+        &["Invalid data found when processing input", "moov atom not found", "Error while decoding", "error while decoding", "Invalid NAL unit", "corrupt"],
+        "The input file is damaged or incomplete",
+        "Check that it plays; re-copy it, or remux it with ffmpeg -i <input> -c copy <fixed>.mkv and queue that",
+    ),
+];
+
+// Each check's words compiled into one pattern, once
+fn compile(checks: &'static [Check]) -> Vec<(Regex, &'static Check)> {
+    checks.iter().map(|check| (ffmpeg::words_regex(check.0), check)).collect()
+}
+
+fn find(checks: &[(Regex, &Check)], text: &str) -> Option<Diagnosis> {
+    checks.iter().find(|(words, _)| words.is_match(text)).map(|(_, (_, problem, fix))| Diagnosis::new(*problem, fix))
+}
+
+// `output` is everything the job's ffmpeg commands printed, `error` the message it failed with
+pub fn diagnose(output: &[String], error: &str) -> Option<Diagnosis> {
+    static MACHINE: LazyLock<Vec<(Regex, &Check)>> = LazyLock::new(|| compile(MACHINE_CHECKS));
+    static SETTINGS: LazyLock<Vec<(Regex, &Check)>> = LazyLock::new(|| compile(SETTINGS_CHECKS));
+    static UNKNOWN_ENCODER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"Unknown encoder '([^']+)'").unwrap());
+
+    let text = ffmpeg::messages(&output.iter().map(String::as_str).chain([error]).collect::<Vec<_>>().join("\n"));
+
+    if let Some(diagnosis) = find(&MACHINE, &text) {
+        return Some(diagnosis);
+    }
+    if let Some(reason) = hwcaps::hardware_problem(&text) {
+        return Some(Diagnosis::new(
+            format!("Hardware encoding or decoding is unavailable: {}", reason),
+            "Pick a software CODEC, set DECODE to Off, or set FALLBACK to Software",
+        ));
+    }
+    if let Some(caps) = UNKNOWN_ENCODER.captures(&text) {
+        return Some(Diagnosis::new(
+            format!("This ffmpeg has no {} encoder", &caps[1]),
+            "Pick another CODEC, or install an ffmpeg build that includes it (see ffmpeg -encoders)",
+        ));
+    }
+    find(&SETTINGS, &text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The start of a job's output at the default loglevel, before anything goes wrong
+    const DUMP: &str = "\
+ffmpeg version 6.1.1 Copyright (c) 2000-2023 the FFmpeg developers
+Input #0, mov,mp4,m4a,3gp,3g2,mj2, from '/videos/corrupt Permission denied.mp4':
+  Metadata:
+    title           : No space left on device
+  Duration: 00:01:00.00, start: 0.000000, bitrate: 8000 kb/s
+  Stream #0:0[0x1](und): Video: h264 (High) (avc1 / 0x31637661), yuv420p, 1920x1080, 25 fps
+Stream mapping:
+  Stream #0:0 -> #0:0 (h264 (native) -> hevc (libx265))
+Press [q] to stop, [?] for help
+frame=  120 fps= 60 q=28.0 size=    1024KiB time=00:00:04.80 bitrate=1747.6kbits/s speed=2.4x";
+
+    fn problem(stderr: &str) -> Option<String> {
+        let output: Vec<String> = DUMP.lines().chain(stderr.lines()).map(String::from).collect();
+        diagnose(&output, "ffmpeg exited with exit status: 1").map(|d| d.problem)
+    }
+
+    #[test]
+    fn machine_problems() {
+        assert_eq!(problem("[out#0/mp4 @ 0x1] Error writing trailer: No space left on device").unwrap(), "The disk is full");
+        assert_eq!(
+            problem("[out#0/mkv @ 0x1] Error opening output /videos/out.mkv: Permission denied").unwrap(),
+            "A file could not be opened: permission denied"
+        );
+    }
+
+    #[test]
+    fn hardware_problems() {
+        assert_eq!(
+            problem("[h264_nvenc @ 0x1] Cannot load libcuda.so.1").unwrap(),
+            "Hardware encoding or decoding is unavailable: no NVIDIA GPU or driver found"
+        );
+    }
+
+    #[test]
+    fn missing_encoders() {
+        assert_eq!(problem("Unknown encoder 'libsvtav1'").unwrap(), "This ffmpeg has no libsvtav1 encoder");
+        assert_eq!(problem("[vost#0:0 @ 0x1] Encoder not found").unwrap(), "This ffmpeg lacks the selected encoder");
+    }
+
+    #[test]
+    fn settings_problems() {
+        assert_eq!(
+            problem("[mp4 @ 0x1] Could not find tag for codec prores in stream #0, codec not currently supported in container").unwrap(),
+            "The output FORMAT can't hold this codec"
+        );
+        assert_eq!(
+            problem("[libx264 @ 0x1] Specified pixel format yuv422p10le is not supported by the libx264 encoder.").unwrap(),
+            "The encoder can't produce the selected pixel format"
+        );
+        assert_eq!(problem("[in#0 @ 0x1] Error opening input: No such file or directory").unwrap(), "A file or folder doesn't exist");
+    }
+
+    #[test]
+    fn damaged_input() {
+        assert_eq!(problem("[mov,mp4,m4a,3gp,3g2,mj2 @ 0x1] moov atom not found").unwrap(), "The input file is damaged or incomplete");
+        assert_eq!(problem("[h264 @ 0x1] corrupt decoded frame in stream 0").unwrap(), "The input file is damaged or incomplete");
+    }
+
+    #[test]
+    fn chunk_output_is_searched_too() {
+        let output = vec!["chunk 3: [libx265 @ 0x1] Error writing trailer: No space left on device".to_string()];
+        assert_eq!(diagnose(&output, "Chunk 3 failed to encode").unwrap().problem, "The disk is full");
+    }
+
+    #[test]
+    fn names_and_titles_are_not_causes() {
+        // The dump mentions "corrupt", "Permission denied" and a full disk in names only
+        assert!(problem("[libx265 @ 0x1] something unexpected").is_none());
+        // Words inside longer words don't count
+        assert!(problem("[h264 @ 0x1] uncorrupted stream state reset").is_none());
+    }
+}
@@ -0,0 +1,126 @@
+// Extra ffmpeg options typed by the user for things the settings grid doesn't model
+// (e.g. `-x265-params "aq-mode=3"`, `-g 48`, `-bf 3`). Input args go right before
+// `-i <input>`, output args after the generated options, right before the output path.
+use crate::ffmpeg::EncodeSettings;
+
+// Options the tool sets itself, and what sets them. A name containing ':' also
+// covers its stream-specific forms (-c:v matches -c:v:0).
+const MANAGED: &[(&[&str], &str)] = &[
+    (&["-i"], "the queued input file"),
+    (&["-y", "-n"], "the overwrite policy"),
+    (&["-progress", "-nostats", "-stats_period", "-loglevel", "-v"], "progress reporting"),
+    (&["-c:v", "-codec:v", "-vcodec", "-c", "-codec"], "the CODEC setting"),
+    (&["-qp", "-crf", "-q", "-q:v", "-qscale", "-qscale:v", "-b", "-b:v", "-vb", "-global_quality", "-rc_mode"], "the QUALITY (QP) setting"),
+    (&["-r"], "the FPS setting"),
+    (&["-pix_fmt"], "the PIXEL FORMAT setting"),
+    (&["-threads"], "the THREADS/JOB setting"),
+    (&["-hwaccel", "-hwaccel_output_format", "-hwaccel_device"], "the DECODE setting"),
+    (&["-init_hw_device", "-filter_hw_device", "-vaapi_device", "-qsv_device"], "the HW DEVICE setting"),
+    (&["-c:a", "-codec:a", "-acodec", "-b:a", "-ab", "-q:a", "-qscale:a", "-aq"], "the audio settings"),
+];
+
+// Options that take no value, so a bare word after them is a stray argument
+const FLAGS: &[&str] = &[
+    "-an", "-vn", "-sn", "-dn", "-shortest", "-re", "-copyts", "-start_at_zero", "-accurate_seek",
+    "-noaccurate_seek", "-autorotate", "-noautorotate", "-hide_banner", "-nostdin", "-stats",
+    "-ignore_unknown", "-copy_unknown", "-xerror", "-benchmark",
+];
+
+// Options that would cut or remap the frames chunked encoding and the quality search line up
+const TRIMMING: &[&str] = &["-ss", "-sseof", "-t", "-to", "-frames", "-frames:v", "-vframes", "-map"];
+
+fn option_matches(arg: &str, name: &str) -> bool {
+    arg == name || (name.contains(':') && arg.starts_with(name) && arg[name.len()..].starts_with(':'))
+}
+
+fn is_option(arg: &str) -> bool {
+    arg.len() > 1 && arg.starts_with('-') && arg.parse::<f64>().is_err()
+}
+
+// Splits the text with shell-style quoting and checks it can be merged safely
+pub fn parse(text: &str) -> Result<Vec<String>, String> {
+    let args = shlex::split(text).ok_or("Unbalanced quotes in extra arguments")?;
+
+    let mut takes_value = false;
+    for arg in &args {
+        if is_option(arg) {
+            if let Some((_, owner)) = MANAGED.iter().find(|(names, _)| names.iter().any(|n| option_matches(arg, n))) {
+                return Err(format!("{} is managed by {} and can't be passed as an extra argument", arg, owner));
+            }
+            takes_value = !FLAGS.contains(&arg.as_str());
+        } else if takes_value {
+            takes_value = false;
+        } else {
+            // ffmpeg would take it as another output file
+            return Err(format!("'{}' is not an option or an option's value", arg));
+        }
+    }
+    Ok(args)
+}
+
+// For encodes that cut the input into pieces themselves
+pub fn check_untrimmed(args: &[String], mode: &str) -> Result<(), String> {
+    match args.iter().find(|arg| TRIMMING.iter().any(|t| option_matches(arg, t))) {
+        Some(arg) => Err(format!("{} can't be used with {}", arg, mode)),
+        None => Ok(()),
+    }
+}
+
+// (input args, output args) of a job
+pub fn for_settings(settings: &EncodeSettings) -> Result<(Vec<String>, Vec<String>), String> {
+    let input = parse(&settings.input_args).map_err(|e| format!("Input args: {}", e))?;
+    let output = parse(&settings.output_args).map_err(|e| format!("Output args: {}", e))?;
+    Ok((input, output))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rejected(text: &str) -> String {
+        parse(text).unwrap_err()
+    }
+
+    #[test]
+    fn options_with_values() {
+        assert_eq!(parse(r#"-x265-params "aq-mode=3:psy-rd=1.0" -g 48"#).unwrap(), ["-x265-params", "aq-mode=3:psy-rd=1.0", "-g", "48"]);
+        // A negative number is a value, not an option
+        assert_eq!(parse("-map_metadata -1").unwrap(), ["-map_metadata", "-1"]);
+        assert_eq!(parse("-an -movflags +faststart").unwrap(), ["-an", "-movflags", "+faststart"]);
+        assert!(parse("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn stray_arguments() {
+        assert_eq!(rejected("-g 48 out.mp4"), "'out.mp4' is not an option or an option's value");
+        assert_eq!(rejected("-an extra"), "'extra' is not an option or an option's value");
+        assert_eq!(rejected("-metadata 'title=x"), "Unbalanced quotes in extra arguments");
+    }
+
+    #[test]
+    fn managed_options() {
+        assert_eq!(rejected("-c:v:0 libx264"), "-c:v:0 is managed by the CODEC setting and can't be passed as an extra argument");
+        assert!(rejected("-crf 20").contains("QUALITY"));
+        for option in ["-b 5M", "-vb 5M", "-q 4", "-qscale 4", "-b:v 5M", "-qscale:v 2"] {
+            assert!(rejected(option).contains("the QUALITY (QP) setting"), "{}", option);
+        }
+        for option in ["-q:a 2", "-aq 2", "-qscale:a 2", "-b:a:0 96k"] {
+            assert!(rejected(option).contains("the audio settings"), "{}", option);
+        }
+        assert!(rejected("-y").contains("overwrite"));
+    }
+
+    #[test]
+    fn other_streams_are_not_managed() {
+        assert_eq!(parse("-c:s mov_text").unwrap(), ["-c:s", "mov_text"]);
+        assert_eq!(parse("-disposition:a:0 default").unwrap(), ["-disposition:a:0", "default"]);
+    }
+
+    #[test]
+    fn trimming_options() {
+        let args = parse("-ss 10 -g 48").unwrap();
+        assert_eq!(check_untrimmed(&args, "chunked encoding").unwrap_err(), "-ss can't be used with chunked encoding");
+        assert!(check_untrimmed(&parse("-frames:v:0 10").unwrap(), "chunked encoding").is_err());
+        assert!(check_untrimmed(&parse("-map_metadata -1 -g 48").unwrap(), "chunked encoding").is_ok());
+    }
+}
@@ -0,0 +1,300 @@
+// Arguments for hardware decoding and for encoders that run on a VAAPI or Quick Sync
+// (QSV) device. Those encoders take frames in GPU memory, so the device is opened
+// before the input and decoded frames are converted and uploaded at the end of the
+// filter chain, unless a hardware decoder already left them on the device.
+// Everything here only builds argument lists, so it can be checked without the hardware.
+use crate::codecs;
+
+pub const DEVICE_AUTO: &str = "Auto";
+
+pub const DECODE_OFF: &str = "Off";
+pub const DECODE_AUTO: &str = "Auto"; // whatever ffmpeg finds, frames come back to system memory
+
+pub fn decode_choices() -> Vec<String> {
+    [DECODE_OFF, DECODE_AUTO, "cuda", "vaapi", "qsv"].map(String::from).to_vec()
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Api {
+    Vaapi,
+    Qsv,
+}
+
+pub fn api(encoder: &str) -> Option<Api> {
+    if encoder.ends_with("_vaapi") {
+        Some(Api::Vaapi)
+    } else if encoder.ends_with("_qsv") {
+        Some(Api::Qsv)
+    } else {
+        None
+    }
+}
+
+// DRM render nodes (/dev/dri/renderD128, ...) to offer in the HW DEVICE setting
+pub fn render_devices() -> Vec<String> {
+    let mut devices: Vec<String> = std::fs::read_dir("/dev/dri")
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.file_name().to_string_lossy().starts_with("renderD"))
+                .map(|entry| entry.path().to_string_lossy().to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+    devices.sort();
+    devices
+}
+
+// Global options that open the device, named so filters can upload to it. QSV on a
+// chosen render node is derived from a VAAPI device on that node.
+pub fn device_args(api: Api, device: Option<&str>) -> Vec<String> {
+    let args: Vec<String> = match (api, device) {
+        (Api::Vaapi, Some(device)) => vec!["-init_hw_device".to_string(), format!("vaapi=va:{}", device)],
+        (Api::Vaapi, None) => vec!["-init_hw_device".to_string(), "vaapi=va".to_string()],
+        (Api::Qsv, Some(device)) => vec![
+            "-init_hw_device".to_string(),
+            format!("vaapi=va:{}", device),
+            "-init_hw_device".to_string(),
+            "qsv=qs@va".to_string(),
+        ],
+        (Api::Qsv, None) => vec!["-init_hw_device".to_string(), "qsv=qs".to_string()],
+    };
+    let name = if api == Api::Vaapi { "va" } else { "qs" };
+    args.into_iter().chain(["-filter_hw_device".to_string(), name.to_string()]).collect()
+}
+
+// Pixel format the frames are converted to before the upload: the hardware's
+// semi-planar NV12/P010 instead of planar yuv420p/yuv420p10le
+pub fn upload_format(pix_fmt: &str) -> &str {
+    match pix_fmt {
+        "yuv420p" => "nv12",
+        "yuv420p10le" => "p010le",
+        other => other,
+    }
+}
+
+// Last filters of the chain. `pix_fmt` None keeps the decoded format.
+pub fn upload_filter(api: Api, pix_fmt: Option<&str>) -> String {
+    // QSV encoders keep a few surfaces of their own on top of the filter's pool
+    let upload = if api == Api::Qsv { "hwupload=extra_hw_frames=64" } else { "hwupload" };
+    match pix_fmt {
+        Some(pix_fmt) => format!("format={},{}", upload_format(pix_fmt), upload),
+        None => upload.to_string(),
+    }
+}
+
+// Constant quality from the QP setting. VAAPI uses constant-QP rate control, QSV its
+// ICQ mode through global_quality; AV1 quantizers run 0-255 on both.
+pub fn quality_args(encoder: &str, qp: &str) -> Vec<String> {
+    let qp = qp.parse::<u32>().unwrap_or(30);
+    let is_av1 = encoder.starts_with("av1_");
+    let value = if is_av1 { codecs::av1_qindex(qp).max(1) } else { qp.clamp(1, 51) }.to_string();
+    match api(encoder) {
+        Some(Api::Vaapi) => {
+            let flag = if is_av1 { "-global_quality" } else { "-qp" };
+            vec!["-rc_mode".to_string(), "CQP".to_string(), flag.to_string(), value]
+        }
+        Some(Api::Qsv) => vec!["-global_quality".to_string(), value],
+        None => Vec::new(),
+    }
+}
+
+fn is_video_filter_option(arg: &str) -> bool {
+    arg == "-vf" || arg == "-filter:v" || arg.starts_with("-filter:v:")
+}
+
+// Adds the upload after the user's own video filters, or as the only -vf. ffmpeg
+// only honours the last -vf, so the two can't simply both be passed.
+pub fn with_upload(output_args: &[String], upload: &str) -> Result<Vec<String>, String> {
+    if let Some(arg) = output_args.iter().find(|arg| arg.starts_with("-filter_complex") || *arg == "-lavfi") {
+        return Err(format!("{} can't be combined with a hardware encoder, use -vf", arg));
+    }
+    let mut args = output_args.to_vec();
+    match args.iter().rposition(|arg| is_video_filter_option(arg)) {
+        Some(i) if i + 1 < args.len() => args[i + 1] = format!("{},{}", args[i + 1], upload),
+        _ => args.extend(["-vf".to_string(), upload.to_string()]),
+    }
+    Ok(args)
+}
+
+fn uses_filters(output_args: &[String]) -> bool {
+    output_args.iter().any(|arg| is_video_filter_option(arg) || arg.starts_with("-filter_complex") || arg == "-lavfi")
+}
+
+// Decoded frames can go straight to the encoder when both run on the same kind of
+// device and no software filters sit in between
+pub fn frames_stay_on_device(decode: &str, encoder: &str, output_args: &[String]) -> bool {
+    let same_device = match decode {
+        "cuda" => encoder.ends_with("_nvenc"),
+        "vaapi" => api(encoder) == Some(Api::Vaapi),
+        "qsv" => api(encoder) == Some(Api::Qsv),
+        _ => false,
+    };
+    same_device && !uses_filters(output_args)
+}
+
+// Input options for the DECODE setting. VAAPI/QSV decoding shares the device the
+// encoder opened (see device_args), or uses the chosen render node otherwise.
+pub fn decode_args(decode: &str, encoder: &str, device: Option<&str>, on_device: bool) -> Vec<String> {
+    if decode == DECODE_OFF {
+        return Vec::new();
+    }
+    // ffmpeg's method names are lower case; "Auto" is only how the setting is shown
+    let method = if decode == DECODE_AUTO { "auto" } else { decode };
+    let mut args = vec!["-hwaccel".to_string(), method.to_string()];
+    match (decode, api(encoder), device) {
+        ("vaapi", Some(Api::Vaapi), _) => args.extend(["-hwaccel_device".to_string(), "va".to_string()]),
+        ("qsv", Some(Api::Qsv), _) => args.extend(["-hwaccel_device".to_string(), "qs".to_string()]),
+        ("vaapi", _, Some(device)) => args.extend(["-hwaccel_device".to_string(), device.to_string()]),
+        ("qsv", _, Some(device)) => args.extend(["-qsv_device".to_string(), device.to_string()]),
+        _ => {}
+    }
+    if on_device {
+        args.extend(["-hwaccel_output_format".to_string(), decode.to_string()]);
+    }
+    args
+}
+
+// Pixel format conversion done on the device when frames stay there. `pix_fmt`
+// None keeps the decoded format, so no filter is needed.
+pub fn device_filter(decode: &str, pix_fmt: Option<&str>) -> Option<String> {
+    let scale = match decode {
+        "cuda" => "scale_cuda",
+        "vaapi" => "scale_vaapi",
+        "qsv" => "scale_qsv",
+        _ => return None,
+    };
+    pix_fmt.map(|pix_fmt| format!("{}=format={}", scale, upload_format(pix_fmt)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NODE: &str = "/dev/dri/renderD128";
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn api_from_encoder_suffix() {
+        assert_eq!(api("hevc_vaapi"), Some(Api::Vaapi));
+        assert_eq!(api("av1_qsv"), Some(Api::Qsv));
+        assert_eq!(api("h264_nvenc"), None);
+        assert_eq!(api("libx264"), None);
+    }
+
+    #[test]
+    fn vaapi_device() {
+        assert_eq!(device_args(Api::Vaapi, None), strings(&["-init_hw_device", "vaapi=va", "-filter_hw_device", "va"]));
+        assert_eq!(
+            device_args(Api::Vaapi, Some(NODE)),
+            strings(&["-init_hw_device", "vaapi=va:/dev/dri/renderD128", "-filter_hw_device", "va"])
+        );
+    }
+
+    #[test]
+    fn qsv_device() {
+        assert_eq!(device_args(Api::Qsv, None), strings(&["-init_hw_device", "qsv=qs", "-filter_hw_device", "qs"]));
+        assert_eq!(
+            device_args(Api::Qsv, Some(NODE)),
+            strings(&[
+                "-init_hw_device",
+                "vaapi=va:/dev/dri/renderD128",
+                "-init_hw_device",
+                "qsv=qs@va",
+                "-filter_hw_device",
+                "qs",
+            ])
+        );
+    }
+
+    #[test]
+    fn upload_converts_to_semi_planar() {
+        assert_eq!(upload_filter(Api::Vaapi, Some("yuv420p")), "format=nv12,hwupload");
+        assert_eq!(upload_filter(Api::Vaapi, Some("yuv420p10le")), "format=p010le,hwupload");
+        assert_eq!(upload_filter(Api::Qsv, Some("yuv420p")), "format=nv12,hwupload=extra_hw_frames=64");
+        assert_eq!(upload_filter(Api::Vaapi, None), "hwupload");
+    }
+
+    #[test]
+    fn upload_without_user_filters() {
+        assert_eq!(with_upload(&[], "format=nv12,hwupload"), Ok(strings(&["-vf", "format=nv12,hwupload"])));
+        assert_eq!(
+            with_upload(&strings(&["-g", "48"]), "format=p010le,hwupload"),
+            Ok(strings(&["-g", "48", "-vf", "format=p010le,hwupload"]))
+        );
+    }
+
+    #[test]
+    fn upload_after_user_filters() {
+        assert_eq!(
+            with_upload(&strings(&["-vf", "scale=1280:-2", "-g", "48"]), "format=nv12,hwupload"),
+            Ok(strings(&["-vf", "scale=1280:-2,format=nv12,hwupload", "-g", "48"]))
+        );
+        // Only the last video filter option counts in ffmpeg
+        assert_eq!(
+            with_upload(&strings(&["-vf", "yadif", "-filter:v", "scale=1280:-2"]), "hwupload"),
+            Ok(strings(&["-vf", "yadif", "-filter:v", "scale=1280:-2,hwupload"]))
+        );
+        assert!(with_upload(&strings(&["-filter_complex", "[0:v]scale=640:-2"]), "hwupload").is_err());
+        assert!(with_upload(&strings(&["-lavfi", "scale=640:-2"]), "hwupload").is_err());
+    }
+
+    #[test]
+    fn vaapi_quality_is_constant_qp() {
+        assert_eq!(quality_args("hevc_vaapi", "24"), strings(&["-rc_mode", "CQP", "-qp", "24"]));
+        assert_eq!(quality_args("h264_vaapi", "0"), strings(&["-rc_mode", "CQP", "-qp", "1"]));
+        assert_eq!(quality_args("h264_vaapi", "53"), strings(&["-rc_mode", "CQP", "-qp", "51"]));
+        assert_eq!(quality_args("av1_vaapi", "30"), strings(&["-rc_mode", "CQP", "-global_quality", "150"]));
+    }
+
+    #[test]
+    fn qsv_quality_is_global_quality() {
+        assert_eq!(quality_args("hevc_qsv", "24"), strings(&["-global_quality", "24"]));
+        assert_eq!(quality_args("av1_qsv", "51"), strings(&["-global_quality", "255"]));
+        assert_eq!(quality_args("av1_qsv", "0"), strings(&["-global_quality", "1"]));
+        assert!(quality_args("libx265", "24").is_empty());
+    }
+
+    #[test]
+    fn frames_stay_on_matching_device_without_filters() {
+        assert!(frames_stay_on_device("vaapi", "hevc_vaapi", &[]));
+        assert!(frames_stay_on_device("qsv", "h264_qsv", &[]));
+        assert!(frames_stay_on_device("cuda", "h264_nvenc", &strings(&["-g", "48"])));
+        assert!(!frames_stay_on_device("vaapi", "hevc_vaapi", &strings(&["-vf", "scale=1280:-2"])));
+        assert!(!frames_stay_on_device("vaapi", "hevc_qsv", &[]));
+        assert!(!frames_stay_on_device(DECODE_AUTO, "hevc_vaapi", &[]));
+        assert!(!frames_stay_on_device(DECODE_OFF, "hevc_vaapi", &[]));
+    }
+
+    #[test]
+    fn decode_on_the_encoder_device() {
+        assert_eq!(
+            decode_args("vaapi", "hevc_vaapi", Some(NODE), true),
+            strings(&["-hwaccel", "vaapi", "-hwaccel_device", "va", "-hwaccel_output_format", "vaapi"])
+        );
+        assert_eq!(
+            decode_args("qsv", "hevc_qsv", None, true),
+            strings(&["-hwaccel", "qsv", "-hwaccel_device", "qs", "-hwaccel_output_format", "qsv"])
+        );
+        assert_eq!(decode_args("cuda", "h264_nvenc", None, true), strings(&["-hwaccel", "cuda", "-hwaccel_output_format", "cuda"]));
+        assert_eq!(device_filter("vaapi", Some("yuv420p10le")), Some("scale_vaapi=format=p010le".to_string()));
+        assert_eq!(device_filter("qsv", Some("yuv420p")), Some("scale_qsv=format=nv12".to_string()));
+        assert_eq!(device_filter("cuda", Some("yuv420p")), Some("scale_cuda=format=nv12".to_string()));
+        assert_eq!(device_filter("vaapi", None), None);
+        assert_eq!(device_filter(DECODE_AUTO, Some("yuv420p")), None);
+    }
+
+    #[test]
+    fn decode_for_a_software_encoder() {
+        assert_eq!(
+            decode_args("vaapi", "libx264", Some(NODE), false),
+            strings(&["-hwaccel", "vaapi", "-hwaccel_device", "/dev/dri/renderD128"])
+        );
+        assert_eq!(decode_args("qsv", "libx264", Some(NODE), false), strings(&["-hwaccel", "qsv", "-qsv_device", "/dev/dri/renderD128"]));
+        assert_eq!(decode_args(DECODE_AUTO, "libx264", None, false), strings(&["-hwaccel", "auto"]));
+        assert!(decode_args(DECODE_OFF, "hevc_vaapi", Some(NODE), false).is_empty());
+    }
+}
@@ -0,0 +1,318 @@
+// Which encoders this machine's ffmpeg can actually use. `ffmpeg -encoders` only
+// says what was compiled in; hardware encoders also need a device and a driver, and
+// some software ones don't run on every CPU (SVT-AV1), so each encoder gets a tiny
+// test encode at startup.
+use std::process::Command;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::LazyLock;
+use std::thread;
+use regex::Regex;
+use crate::codecs;
+use crate::ffmpeg;
+use crate::hwaccel;
+use crate::metrics;
+
+pub struct Capabilities {
+    pub hwaccels: Vec<String>,             // decode acceleration methods, e.g. "cuda", "vaapi"
+    pub unavailable: Vec<(String, String)>, // (encoder, why it can't be used)
+    pub vmaf: bool,                         // libvmaf filter present
+}
+
+// Names from `ffmpeg -encoders`; the list starts after the " ------" legend separator
+pub fn parse_encoders(text: &str) -> Vec<String> {
+    text.lines()
+        .skip_while(|line| !line.trim_start().starts_with("------"))
+        .skip(1)
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .map(|name| name.to_string())
+        .collect()
+}
+
+// Methods listed by `ffmpeg -hwaccels` after its "Hardware acceleration methods:" header
+pub fn parse_hwaccels(text: &str) -> Vec<String> {
+    text.lines()
+        .skip_while(|line| !line.starts_with("Hardware acceleration methods"))
+        .skip(1)
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| line.to_string())
+        .collect()
+}
+
+pub fn is_hardware(encoder: &str) -> bool {
+    ["nvenc", "vaapi", "qsv", "amf", "videotoolbox", "v4l2m2m"].iter().any(|hw| encoder.contains(hw))
+}
+
+// One small black frame through the encoder; enough to open the device and driver.
+// VAAPI/QSV encoders get the frame uploaded to `device` as a real encode would, the
+// others get the pixel format and profile their default settings use.
+pub fn test_command(encoder: &str, device: Option<&str>) -> Command {
+    let mut cmd = ffmpeg::ffmpeg_command();
+    cmd.args(["-hide_banner", "-nostdin", "-v", "error"]);
+    if let Some(api) = hwaccel::api(encoder) {
+        cmd.args(hwaccel::device_args(api, device));
+    }
+    cmd.args(["-f", "lavfi", "-i", "color=black:s=256x256:d=0.1", "-frames:v", "1"]);
+    let profile = codecs::profile_choices(encoder)[0];
+    match hwaccel::api(encoder) {
+        Some(api) => cmd.args(["-vf", &hwaccel::upload_filter(api, Some("yuv420p"))]),
+        None => {
+            let pix_fmt = codecs::resolve_pix_fmt(encoder, profile, codecs::PIX_FMT_AUTO).ok().flatten();
+            cmd.args(["-pix_fmt", pix_fmt.as_deref().unwrap_or("yuv420p")])
+        }
+    };
+    if profile != codecs::PROFILE_DEFAULT {
+        cmd.args(["-profile:v", profile]);
+    }
+    cmd.args(["-c:v", encoder, "-f", "null", "-"]);
+    cmd
+}
+
+// Why a GPU encoder or decoder couldn't start, if its stderr says so. Also used to
+// diagnose failed jobs, so only ffmpeg's messages are searched, never the stream dump.
+pub fn hardware_problem(stderr: &str) -> Option<&'static str> {
+    static KNOWN: LazyLock<Vec<(Regex, &str)>> = LazyLock::new(|| {
+        [
+            (&["libcuda", "No NVENC capable devices", "CUDA_ERROR_NO_DEVICE", "no CUDA-capable device"][..], "no NVIDIA GPU or driver found"),
+            (&["required nvenc API version", "minimum required Nvidia driver"], "NVIDIA driver too old for this ffmpeg"),
+            (&["OpenEncodeSessionEx failed"], "GPU has no free encoder session"),
+            (&["No VA display", "Failed to initialise VAAPI", "vaInitialize failed", "No usable encoding profile"], "no usable VAAPI device"),
+            (&["MFX session", "Error initializing an MFX", "mfxSession"], "no usable Intel Quick Sync device"),
+            (&["amfrt64.dll", "libamfrt64", "AMF failed", "AMF initialisation failed"], "no AMD AMF runtime found"),
+        ]
+        .into_iter()
+        .map(|(words, reason)| (ffmpeg::words_regex(words), reason))
+        .collect()
+    });
+    let messages = ffmpeg::messages(stderr);
+    KNOWN.iter().find(|(words, _)| words.is_match(&messages)).map(|(_, reason)| *reason)
+}
+
+// Turns the test encode's stderr into a short reason for the UI
+pub fn explain_failure(stderr: &str) -> String {
+    if let Some(reason) = hardware_problem(stderr) {
+        return reason.to_string();
+    }
+    match stderr.lines().rev().find(|l| !l.trim().is_empty()) {
+        Some(line) => format!("test encode failed: {}", line.trim()),
+        None => "test encode failed".to_string(),
+    }
+}
+
+fn run_listing(flag: &str) -> Result<String, String> {
+    let output = ffmpeg::ffmpeg_command()
+        .args(["-hide_banner", flag])
+        .output()
+        .map_err(|e| format!("Cannot run ffmpeg: {}", e))?;
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+// Checks every encoder in `candidates` with test encodes run in parallel.
+// Also settles whether libvmaf is present, so the UI never waits for ffmpeg.
+pub fn detect(candidates: &[String], device: Option<&str>) -> Result<Capabilities, String> {
+    let compiled_in = parse_encoders(&run_listing("-encoders")?);
+    if compiled_in.is_empty() {
+        return Err("ffmpeg -encoders listed no encoders".to_string());
+    }
+    let hwaccels = parse_hwaccels(&run_listing("-hwaccels")?);
+
+    let unavailable = thread::scope(|scope| {
+        let checks: Vec<_> = candidates
+            .iter()
+            .map(|encoder| {
+                let present = compiled_in.contains(encoder);
+                scope.spawn(move || {
+                    if !present {
+                        return Some("not included in this ffmpeg build".to_string());
+                    }
+                    match test_command(encoder, device).output() {
+                        Ok(output) if output.status.success() => None,
+                        Ok(output) => Some(explain_failure(&String::from_utf8_lossy(&output.stderr))),
+                        Err(e) => Some(format!("test encode could not run: {}", e)),
+                    }
+                })
+            })
+            .collect();
+        candidates
+            .iter()
+            .zip(checks)
+            .filter_map(|(encoder, check)| check.join().ok().flatten().map(|reason| (encoder.clone(), reason)))
+            .collect()
+    });
+
+    Ok(Capabilities { hwaccels, unavailable, vmaf: metrics::vmaf_available() })
+}
+
+// Runs `detect` in the background so startup isn't held up by GPU initialisation
+pub fn start_detection(candidates: Vec<String>, device: Option<String>) -> Receiver<Result<Capabilities, String>> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = tx.send(detect(&candidates, device.as_deref()));
+    });
+    rx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENCODERS: &str = "\
+Encoders:
+ V..... = Video
+ A..... = Audio
+ S..... = Subtitle
+ .F.... = Frame-level multithreading
+ ..S... = Slice-level multithreading
+ ...X.. = Codec is experimental
+ ....B. = Supports draw_horiz_band
+ .....D = Supports direct rendering method 1
+ ------
+ V....D libx264              libx264 H.264 / AVC / MPEG-4 AVC / MPEG-4 part 10 (codec h264)
+ V....D h264_nvenc           NVIDIA NVENC H.264 encoder (codec h264)
+ V..... h264_qsv             H.264 / AVC / MPEG-4 AVC / MPEG-4 part 10 (Intel Quick Sync Video acceleration) (codec h264)
+ V....D hevc_vaapi           H.265/HEVC (VAAPI) (codec hevc)
+ V.S... prores_ks            Apple ProRes (iCodec Pro) (codec prores)
+ A....D aac                  AAC (Advanced Audio Coding)
+";
+
+    const HWACCELS: &str = "\
+Hardware acceleration methods:
+vdpau
+cuda
+vaapi
+qsv
+drm
+
+";
+
+    const NVENC_NO_DRIVER: &str = "\
+[h264_nvenc @ 0x55d5c8a4e2c0] Cannot load libcuda.so.1
+[h264_nvenc @ 0x55d5c8a4e2c0] Error while opening encoder - maybe incorrect parameters such as bit_rate, rate, width or height
+[vost#0:0/h264_nvenc @ 0x55d5c8a4d780] Error while opening encoder - maybe incorrect parameters such as bit_rate, rate, width or height.
+Error while filtering: Generic error in an external library
+";
+
+    const NVENC_OLD_DRIVER: &str = "\
+[hevc_nvenc @ 0x5623a1f0b2c0] Driver does not support the required nvenc API version. Required: 12.1 Found: 12.0
+[hevc_nvenc @ 0x5623a1f0b2c0] The minimum required Nvidia driver for nvenc is 530.41.03 or newer
+[vost#0:0/hevc_nvenc @ 0x5623a1f0a780] Error while opening encoder - maybe incorrect parameters such as bit_rate, rate, width or height.
+";
+
+    const NVENC_BUSY: &str = "\
+[h264_nvenc @ 0x5581bc4f8a80] OpenEncodeSessionEx failed: out of memory (10): (no details)
+[vost#0:0/h264_nvenc @ 0x5581bc4f7c40] Error while opening encoder - maybe incorrect parameters such as bit_rate, rate, width or height.
+";
+
+    const VAAPI_NO_DEVICE: &str = "\
+[AVHWDeviceContext @ 0x5581bc4f8a80] No VA display found for device /dev/dri/renderD128.
+Device creation failed: -22.
+Failed to set value 'vaapi=va:/dev/dri/renderD128' for option 'init_hw_device': Invalid argument
+Error parsing global options: Invalid argument
+";
+
+    const QSV_NO_DEVICE: &str = "\
+[AVHWDeviceContext @ 0x55f1b7a3c0c0] Error initializing an MFX session: -3.
+Device creation failed: -1313558101.
+Failed to set value 'qsv=qs' for option 'init_hw_device': Unknown error occurred
+Error parsing global options: Unknown error occurred
+";
+
+    // A job's output at the default loglevel; the file and its title mention AMF
+    const AMF_IN_NAMES: &str = "\
+ffmpeg version 6.1.1 Copyright (c) 2000-2023 the FFmpeg developers
+  configuration: --enable-gpl --enable-libx264 --enable-amf
+Input #0, mov,mp4,m4a,3gp,3g2,mj2, from 'AMF launch.mp4':
+  Metadata:
+    title           : AMF keynote
+  Duration: 00:01:00.00, start: 0.000000, bitrate: 8000 kb/s
+  Stream #0:0[0x1](und): Video: h264 (High) (avc1 / 0x31637661), yuv420p, 1920x1080, 7800 kb/s, 25 fps
+Stream mapping:
+  Stream #0:0 -> #0:0 (h264 (native) -> h264 (libx264))
+Output #0, matroska, to 'AMF launch_encoded.mkv':
+chunk 2: Input #0, mov,mp4,m4a,3gp,3g2,mj2, from 'AMF launch.mp4':
+chunk 2:     title           : AMF keynote
+[libx264 @ 0x55d5c8a4e2c0] broken
+Conversion failed!
+";
+
+    const AMF_NO_RUNTIME: &str = "\
+[h264_amf @ 0x55d5c8a4e2c0] DLL amfrt64.dll failed to open
+[vost#0:0/h264_amf @ 0x55d5c8a4d780] Error while opening encoder - maybe incorrect parameters such as bit_rate, rate, width or height.
+";
+
+    #[test]
+    fn encoders_after_the_legend() {
+        assert_eq!(parse_encoders(ENCODERS), ["libx264", "h264_nvenc", "h264_qsv", "hevc_vaapi", "prores_ks", "aac"]);
+        assert!(parse_encoders("").is_empty());
+    }
+
+    #[test]
+    fn hwaccel_methods() {
+        assert_eq!(parse_hwaccels(HWACCELS), ["vdpau", "cuda", "vaapi", "qsv", "drm"]);
+        assert!(parse_hwaccels("Hardware acceleration methods:\n\n").is_empty());
+    }
+
+    #[test]
+    fn hardware_encoders() {
+        assert!(is_hardware("h264_nvenc"));
+        assert!(is_hardware("hevc_vaapi"));
+        assert!(is_hardware("av1_qsv"));
+        assert!(!is_hardware("libx265"));
+    }
+
+    fn args(cmd: &Command) -> Vec<String> {
+        cmd.get_args().map(|a| a.to_string_lossy().to_string()).collect()
+    }
+
+    #[test]
+    fn test_encodes_use_the_default_format() {
+        let x264 = args(&test_command("libx264", None));
+        assert!(x264.windows(2).any(|w| w == ["-pix_fmt", "yuv420p"]));
+        assert!(!x264.contains(&"-profile:v".to_string()));
+
+        let prores = args(&test_command("prores_ks", None));
+        assert!(prores.windows(2).any(|w| w == ["-pix_fmt", "yuv422p10le"]));
+        assert!(prores.windows(2).any(|w| w == ["-profile:v", "hq"]));
+
+        let dnxhr = args(&test_command("dnxhd", None));
+        assert!(dnxhr.windows(2).any(|w| w == ["-profile:v", "dnxhr_hq"]));
+        assert!(args(&test_command("libx264rgb", None)).windows(2).any(|w| w == ["-pix_fmt", "rgb24"]));
+
+        let vaapi = args(&test_command("hevc_vaapi", Some("/dev/dri/renderD128")));
+        assert!(!vaapi.contains(&"-pix_fmt".to_string()));
+        assert!(vaapi.contains(&"-vf".to_string()));
+    }
+
+    #[test]
+    fn nvenc_failures() {
+        assert_eq!(explain_failure(NVENC_NO_DRIVER), "no NVIDIA GPU or driver found");
+        assert_eq!(explain_failure(NVENC_OLD_DRIVER), "NVIDIA driver too old for this ffmpeg");
+        assert_eq!(explain_failure(NVENC_BUSY), "GPU has no free encoder session");
+    }
+
+    #[test]
+    fn vaapi_and_qsv_failures() {
+        assert_eq!(explain_failure(VAAPI_NO_DEVICE), "no usable VAAPI device");
+        assert_eq!(explain_failure(QSV_NO_DEVICE), "no usable Intel Quick Sync device");
+    }
+
+    #[test]
+    fn amf_failures() {
+        assert_eq!(explain_failure(AMF_NO_RUNTIME), "no AMD AMF runtime found");
+        assert_eq!(hardware_problem("[h264_amf @ 0x1] AMF failed to initialise on the given D3D11 device"), Some("no AMD AMF runtime found"));
+    }
+
+    #[test]
+    fn names_in_the_stream_dump_are_not_errors() {
+        assert_eq!(hardware_problem(AMF_IN_NAMES), None);
+        assert_eq!(hardware_problem("[libx264 @ 0x1] Error opening output AMFtest.mkv"), None);
+        assert_eq!(hardware_problem("[libx264 @ 0x1] libcudart missing"), None);
+    }
+
+    #[test]
+    fn unknown_failure_shows_the_last_line() {
+        assert_eq!(hardware_problem("[libx264 @ 0x1] broken\nConversion failed!\n\n"), None);
+        assert_eq!(explain_failure("[libx264 @ 0x1] broken\nConversion failed!\n\n"), "test encode failed: Conversion failed!");
+        assert_eq!(explain_failure(""), "test encode failed");
+    }
+}
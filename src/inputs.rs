@@ -0,0 +1,217 @@
+// Command line input handling: options, directory recursion and glob expansion
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use glob::Pattern;
+use crate::output;
+use crate::paths;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SortOrder {
+    Name,
+    Size,
+    Modified,
+}
+
+#[derive(Clone)]
+pub struct InputOptions {
+    pub extensions: Vec<String>,
+    pub include: Vec<Pattern>,
+    pub exclude: Vec<Pattern>,
+    pub max_depth: Option<usize>, // 0 = only files directly inside the directory
+    pub sort: SortOrder,
+    // Encoded files go here, recreating the folder layout below each input directory
+    pub output_root: Option<PathBuf>,
+}
+
+impl Default for InputOptions {
+    fn default() -> Self {
+        Self {
+            extensions: paths::VIDEO_EXTENSIONS.iter().map(|e| e.to_string()).collect(),
+            include: Vec::new(),
+            exclude: Vec::new(),
+            max_depth: None,
+            sort: SortOrder::Name,
+            output_root: None,
+        }
+    }
+}
+
+pub const USAGE: &str = "Usage: VideoEncoder [--ext mp4,mkv] [--include GLOB] [--exclude GLOB] [--max-depth N] \
+[--sort name|size|mtime] [--output-root DIR] [--output-dir DIR] [--template TEMPLATE] <files, directories or globs>...";
+
+// Everything given on the command line
+pub struct CliArgs {
+    pub input: InputOptions,
+    pub output_dir: Option<PathBuf>, // flat output directory for every entry
+    pub template: Option<String>,
+    pub paths: Vec<String>,
+}
+
+// A file found from the command line, and where its output should go
+pub struct ExpandedInput {
+    pub path: PathBuf,
+    pub output_dir: Option<PathBuf>,
+}
+
+pub fn parse_args(args: Vec<String>) -> Result<CliArgs, String> {
+    let mut options = InputOptions::default();
+    let mut output_dir = None;
+    let mut template = None;
+    let mut inputs = Vec::new();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| format!("{} needs a value. {}", name, USAGE));
+        match arg.as_str() {
+            "--ext" => {
+                options.extensions = value("--ext")?
+                    .split(',')
+                    .map(|e| e.trim().trim_start_matches('.').to_lowercase())
+                    .filter(|e| !e.is_empty())
+                    .collect();
+            }
+            "--include" => options.include.push(parse_pattern(&value("--include")?)?),
+            "--exclude" => options.exclude.push(parse_pattern(&value("--exclude")?)?),
+            "--max-depth" => {
+                let depth = value("--max-depth")?;
+                options.max_depth = Some(depth.parse().map_err(|_| format!("Invalid --max-depth: {}", depth))?);
+            }
+            "--sort" => {
+                options.sort = match value("--sort")?.as_str() {
+                    "name" => SortOrder::Name,
+                    "size" => SortOrder::Size,
+                    "mtime" => SortOrder::Modified,
+                    other => return Err(format!("Invalid --sort: {} (expected name, size or mtime)", other)),
+                };
+            }
+            "--output-root" => options.output_root = Some(PathBuf::from(value("--output-root")?)),
+            "--output-dir" => output_dir = Some(PathBuf::from(value("--output-dir")?)),
+            "--template" => {
+                let t = value("--template")?;
+                output::validate_template(&t)?;
+                template = Some(t);
+            }
+            _ => inputs.push(arg),
+        }
+    }
+
+    Ok(CliArgs { input: options, output_dir, template, paths: inputs })
+}
+
+fn parse_pattern(pattern: &str) -> Result<Pattern, String> {
+    Pattern::new(pattern).map_err(|e| format!("Invalid pattern {}: {}", pattern, e))
+}
+
+// Expands one input argument: a file, a directory (recursively) or a glob
+pub fn expand(arg: &str, options: &InputOptions) -> Result<Vec<ExpandedInput>, String> {
+    let path = Path::new(arg);
+
+    if path.is_dir() {
+        return Ok(expand_directory(path, options));
+    }
+    if path.exists() {
+        let output_dir = options.output_root.clone();
+        return Ok(vec![ExpandedInput { path: path.to_path_buf(), output_dir }]);
+    }
+
+    // Windows shells don't expand wildcards, so do it here
+    if arg.contains(['*', '?', '[']) {
+        let matches = glob::glob(arg).map_err(|e| format!("Invalid glob {}: {}", arg, e))?;
+        let base = glob_base(arg);
+        let mut found = Vec::new();
+        for entry in matches.flatten() {
+            if entry.is_dir() {
+                found.extend(expand_directory(&entry, options));
+            } else if has_extension(&entry, options) && matches_filters(&base, &entry, options) {
+                found.push(ExpandedInput { path: entry, output_dir: options.output_root.clone() });
+            }
+        }
+        if found.is_empty() {
+            return Err(format!("No files match: {}", arg));
+        }
+        return Ok(found);
+    }
+
+    Err(format!("File not found: {}", arg))
+}
+
+// The directory part of a glob before its first wildcard, e.g. "videos/2024" for
+// "videos/2024/*/clip?.mp4"; filters see matched files relative to it
+fn glob_base(pattern: &str) -> PathBuf {
+    Path::new(pattern)
+        .components()
+        .take_while(|c| !c.as_os_str().to_string_lossy().contains(['*', '?', '[']))
+        .collect()
+}
+
+fn expand_directory(root: &Path, options: &InputOptions) -> Vec<ExpandedInput> {
+    let mut files = Vec::new();
+    walk(root, root, 0, options, &mut HashSet::new(), &mut files);
+    sort_files(&mut files, options.sort);
+
+    files
+        .into_iter()
+        .map(|path| {
+            // <output root>/<input dir name>/<subfolders>/
+            let output_dir = options.output_root.as_ref().map(|out| {
+                let relative_dir = path.parent().and_then(|p| p.strip_prefix(root).ok()).unwrap_or(Path::new(""));
+                let root_name = root.canonicalize().ok().and_then(|r| r.file_name().map(|n| n.to_os_string()));
+                match root_name {
+                    Some(name) => out.join(name).join(relative_dir),
+                    None => out.join(relative_dir),
+                }
+            });
+            ExpandedInput { path, output_dir }
+        })
+        .collect()
+}
+
+// Symlinked directories are followed, but each real directory is read only once,
+// so a link back up the tree can't loop forever
+fn walk(
+    root: &Path,
+    dir: &Path,
+    depth: usize,
+    options: &InputOptions,
+    visited: &mut HashSet<PathBuf>,
+    files: &mut Vec<PathBuf>,
+) {
+    let Ok(real) = dir.canonicalize() else { return };
+    if !visited.insert(real) {
+        return;
+    }
+    let Ok(read_dir) = std::fs::read_dir(dir) else { return };
+    for entry in read_dir.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if options.max_depth.is_none_or(|max| depth < max) {
+                walk(root, &path, depth + 1, options, visited, files);
+            }
+        } else if has_extension(&path, options) && matches_filters(root, &path, options) {
+            files.push(path);
+        }
+    }
+}
+
+fn has_extension(path: &Path, options: &InputOptions) -> bool {
+    path.extension()
+        .map(|ext| options.extensions.contains(&ext.to_string_lossy().to_lowercase()))
+        .unwrap_or(false)
+}
+
+// Patterns match either the path relative to the input directory or the bare file name
+fn matches_filters(root: &Path, path: &Path, options: &InputOptions) -> bool {
+    let relative = path.strip_prefix(root).unwrap_or(path).to_string_lossy().replace('\\', "/");
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let matches = |p: &Pattern| p.matches(&relative) || p.matches(&name);
+
+    (options.include.is_empty() || options.include.iter().any(matches)) && !options.exclude.iter().any(matches)
+}
+
+fn sort_files(files: &mut [PathBuf], order: SortOrder) {
+    match order {
+        SortOrder::Name => files.sort(),
+        SortOrder::Size => files.sort_by_key(|p| std::fs::metadata(p).map(|m| m.len()).unwrap_or(0)),
+        SortOrder::Modified => files.sort_by_key(|p| std::fs::metadata(p).and_then(|m| m.modified()).ok()),
+    }
+}
@@ -0,0 +1,88 @@
+// Job logs: every line a queue entry's runs produce is kept for the log viewer and
+// appended to a file per entry in the log directory, so it outlives the session.
+// ffmpeg doesn't mark its errors and warnings, so lines are sorted into levels by
+// what they say.
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use crate::output;
+
+// Lines of the system log kept for the viewer
+pub const SYSTEM_HISTORY: usize = 5000;
+
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+pub enum Level {
+    Progress, // ffmpeg's frame=/size= status lines
+    Info,
+    Warning,
+    Error,
+}
+
+impl Level {
+    // As a minimum level for the viewer's filter
+    pub fn label(&self) -> &'static str {
+        match self {
+            Level::Progress => "all",
+            Level::Info => "info",
+            Level::Warning => "warning",
+            Level::Error => "error",
+        }
+    }
+
+    pub fn next(&self) -> Level {
+        match self {
+            Level::Progress => Level::Info,
+            Level::Info => Level::Warning,
+            Level::Warning => Level::Error,
+            Level::Error => Level::Progress,
+        }
+    }
+}
+
+pub fn level(line: &str) -> Level {
+    let line = line.trim_start();
+    if line.starts_with("frame=") || line.starts_with("size=") {
+        return Level::Progress;
+    }
+    let lower = line.to_lowercase();
+    let has = |patterns: &[&str]| patterns.iter().any(|p| lower.contains(p));
+    if has(&["error", "failed", "invalid", "cannot", "could not", "not found", "no such file", "permission denied", "unknown encoder"]) {
+        Level::Error
+    } else if has(&["warning", "deprecated", "retrying", "skipped", "cancel"]) {
+        Level::Warning
+    } else {
+        Level::Info
+    }
+}
+
+// Default log directory, ~/.local/share/videoencoder/logs on Linux
+pub fn default_dir() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|dir| dir.join("videoencoder").join("logs"))
+}
+
+// One entry's log file, open for appending while the entry is in the queue
+pub struct LogFile {
+    pub path: PathBuf,
+    file: File,
+}
+
+impl LogFile {
+    // Named after the start time and input: 2024-05-01_153012_movie_3.log
+    pub fn create(dir: &Path, input: &str, id: usize) -> Result<Self, String> {
+        std::fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+        let stem = Path::new(input).file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+        let (date, time) = output::now_utc();
+        let path = dir.join(format!("{}_{}_{}_{}.log", date, time, stem, id));
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+        Ok(Self { path, file })
+    }
+
+    // Written straight through, so the file is complete even if the program is killed
+    pub fn write(&mut self, line: &str) {
+        let _ = writeln!(self.file, "{}", line);
+    }
+}
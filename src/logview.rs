@@ -0,0 +1,101 @@
+// Full-screen log viewer overlay: the system log or one queue entry's log,
+// filtered by level, with scrolling and a case-insensitive search
+use crate::logs::{self, Level};
+
+pub struct LogViewer {
+    pub source: Option<usize>, // id of the queue entry shown, None for the system log
+    pub top: Option<usize>,    // first line shown; None follows the end of the log
+    pub min_level: Level,
+    pub search: String,
+    pub typing: bool,                 // the search is being typed
+    pub current_match: Option<usize>, // line the last search jump landed on
+}
+
+// Log lines that fit on the screen: everything but the borders and the hint line
+pub fn page_height() -> usize {
+    crossterm::terminal::size().map(|(_, rows)| rows as usize).unwrap_or(24).saturating_sub(3).max(1)
+}
+
+impl LogViewer {
+    pub fn new(source: Option<usize>) -> Self {
+        Self { source, top: None, min_level: Level::Info, search: String::new(), typing: false, current_match: None }
+    }
+
+    // Lines passing the level filter
+    pub fn filtered<'a>(&self, lines: &'a [String]) -> Vec<&'a str> {
+        lines.iter().filter(|line| logs::level(line) >= self.min_level).map(String::as_str).collect()
+    }
+
+    pub fn first_line(&self, total: usize, height: usize) -> usize {
+        let last_page = total.saturating_sub(height);
+        self.top.unwrap_or(last_page).min(last_page)
+    }
+
+    pub fn scroll_up(&mut self, lines: usize, total: usize, height: usize) {
+        self.top = Some(self.first_line(total, height).saturating_sub(lines));
+    }
+
+    pub fn scroll_down(&mut self, lines: usize, total: usize, height: usize) {
+        self.show_from(self.first_line(total, height) + lines, total, height);
+    }
+
+    pub fn go_to_start(&mut self) {
+        self.top = Some(0);
+    }
+
+    pub fn go_to_end(&mut self) {
+        self.top = None;
+    }
+
+    // Reaching the last page goes back to following new lines
+    fn show_from(&mut self, top: usize, total: usize, height: usize) {
+        self.top = if top + height >= total { None } else { Some(top) };
+    }
+
+    // Another source or filter: back to the end, forgetting the search position
+    pub fn reset_position(&mut self) {
+        self.top = None;
+        self.current_match = None;
+    }
+
+    pub fn cycle_level(&mut self) {
+        self.min_level = self.min_level.next();
+        self.reset_position();
+    }
+
+    pub fn matches(&self, line: &str) -> bool {
+        !self.match_ranges(line).is_empty()
+    }
+
+    // Byte ranges of the search text in `line`. ASCII-only case folding keeps the
+    // offsets valid for the original line.
+    pub fn match_ranges(&self, line: &str) -> Vec<(usize, usize)> {
+        if self.search.is_empty() {
+            return Vec::new();
+        }
+        let needle = self.search.to_ascii_lowercase();
+        line.to_ascii_lowercase().match_indices(&needle).map(|(start, found)| (start, start + found.len())).collect()
+    }
+
+    // Jumps to the next match after (or, going `older`, before) the last one, wrapping
+    // around the log. Without an earlier match the search starts below the screen, so
+    // the first jump back lands on the newest match in view.
+    pub fn jump_to_match(&mut self, lines: &[&str], older: bool, height: usize) {
+        let total = lines.len();
+        if total == 0 || self.search.is_empty() {
+            return;
+        }
+        let from = self.current_match.unwrap_or_else(|| (self.first_line(total, height) + height).min(total));
+        let found = if older {
+            (0..from).rev().chain((from..total).rev()).find(|&i| self.matches(lines[i]))
+        } else {
+            (from + 1..total).chain(0..=from.min(total - 1)).find(|&i| self.matches(lines[i]))
+        };
+        let Some(index) = found else { return };
+        self.current_match = Some(index);
+        let first = self.first_line(total, height);
+        if index < first || index >= first + height {
+            self.show_from(index.saturating_sub(height / 2), total, height);
+        }
+    }
+}
@@ -1,10 +1,34 @@
 mod app;
 mod ui;
 mod ffmpeg;
+mod codecs;
+mod chunked;
+mod queue;
+mod paths;
+mod probe;
+mod browser;
+mod inputs;
+mod output;
+mod verify;
+mod metrics;
+mod search;
+mod config;
+mod presets;
+mod extra_args;
+mod preview;
+mod hwcaps;
+mod hwaccel;
+mod retry;
+mod diagnostics;
+mod logs;
+mod logview;
 
 use anyhow::Result;
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyEventKind},
+    event::{
+        self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture, Event, KeyCode,
+        KeyEventKind,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
@@ -12,18 +36,27 @@ use ratatui::{
     backend::CrosstermBackend,
     Terminal,
 };
-use std::{io, time::Duration};
+use std::{io, time::{Duration, Instant}};
 use app::App;
 use ui::ui;
 
 use std::sync::mpsc::{self, Receiver};
-use ffmpeg::{start_encoding, FfmpegEvent};
+use ffmpeg::FfmpegEvent;
+
+// Fewer keystrokes arriving at once are never taken for a drop
+const PASTE_BURST_KEYS: usize = 8;
+// A typed-out drop arrives in pieces, but with shorter gaps than this
+const BURST_IDLE: Duration = Duration::from_millis(20);
+// Stop collecting a burst after this long, so a held-down key can't stall the UI
+const MAX_BURST: Duration = Duration::from_secs(1);
 
 fn main() -> Result<()> {
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    // Not every terminal supports bracketed paste; drops then arrive as key bursts
+    let _ = execute!(stdout, EnableBracketedPaste);
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
@@ -40,71 +73,66 @@ fn main() -> Result<()> {
     loop {
         terminal.draw(|f| ui(f, &app))?;
 
+        if let Some(browser) = app.browser.as_mut() {
+            browser.poll_durations();
+        }
+        app.poll_encoder_detection();
+        app.poll_resolutions();
+
         // Check for FFmpeg events
         while let Ok(event) = rx.try_recv() {
-            match event {
-                FfmpegEvent::Progress(p, fps, speed, bitrate, time) => {
-                    app.progress = p;
-                    app.fps = fps;
-                    app.speed = speed;
-                    app.bitrate = bitrate;
-                    app.time = time;
-                }
-                FfmpegEvent::Log(msg) => {
-                    app.log_messages.push(msg);
-                    if app.log_messages.len() > 100 {
-                        app.log_messages.remove(0);
-                    }
-                }
-                FfmpegEvent::Done => {
-                    app.is_encoding = false;
-                    app.progress = 1.0;
-                    app.log_messages.push("Encoding Finished!".to_string());
-                    
-                    // Mark current file as done
-                    if let Some((input, _)) = app.get_current_file() {
-                        app.completed_files.push(input);
-                    }
-                    
-                    // Move to next file
-                    app.current_file_index += 1;
-                    
-                    // Check if there are more files
-                    if let Some((input, mut output)) = app.get_current_file() {
-                         app.is_encoding = true;
-                         app.progress = 0.0;
-                         app.log_messages.push(format!("Starting next file: {}", input));
-                         
-                         let encoder = app.encoders[app.selected_encoder_index].clone();
-                         let qp = app.qp_list[app.selected_qp_index].clone();
-                         let audio_bitrate = app.audio_bitrate_list[app.selected_audio_bitrate_index].clone();
-                         let fps = app.fps_list[app.selected_fps_index].clone();
-                         
-                         // Update output extension based on container
-                         let container = app.container_list[app.selected_container_index].clone();
-                         let path = std::path::Path::new(&output);
-                         output = path.with_extension(&container).to_string_lossy().to_string();
-
-                         let tx_next = tx.clone();
-                         
-                         start_encoding(input, output, encoder, qp, audio_bitrate, fps, tx_next);
-                    } else {
-                        app.log_messages.push("All files processed!".to_string());
-                    }
-                }
-                FfmpegEvent::Error(msg) => {
-                    app.is_encoding = false;
-                    app.log_messages.push(format!("ERROR: {}", msg));
-                }
-            }
+            app.handle_ffmpeg_event(event, &tx);
         }
 
         if event::poll(Duration::from_millis(100))? {
-            if let Event::Key(key) = event::read()? {
-                if key.kind == KeyEventKind::Press {
-                    app.handle_key_event(key, tx.clone());
+            // Take everything up to the point the input goes quiet, so a paste typed
+            // out as keystrokes can be told apart from a user pressing keys. A drop
+            // split across two polls would otherwise be judged in halves, its second
+            // half replayed as keys (and a `q` in it quitting).
+            let mut events = vec![event::read()?];
+            let burst_end = Instant::now() + MAX_BURST;
+            while Instant::now() < burst_end && event::poll(BURST_IDLE)? {
+                events.push(event::read()?);
+            }
+
+            let pressed: Vec<KeyCode> = events
+                .iter()
+                .filter_map(|e| match e {
+                    Event::Key(key) if key.kind == KeyEventKind::Press => Some(key.code),
+                    _ => None,
+                })
+                .collect();
+            let typed: String = pressed
+                .iter()
+                .filter_map(|code| match code {
+                    KeyCode::Char(c) => Some(*c),
+                    KeyCode::Enter => Some('\n'),
+                    KeyCode::Tab => Some('\t'),
+                    _ => None,
+                })
+                .collect();
+
+            // Bracketed paste delivers drops as Event::Paste. Terminals without it
+            // type them out, so a burst is taken for a drop only when it is nothing
+            // but text naming files that exist; key repeat and keys queued while the
+            // UI was busy stay keystrokes.
+            let dropped = pressed.len() >= PASTE_BURST_KEYS
+                && typed.chars().count() == pressed.len()
+                && paths::names_existing_files(&typed);
+            if dropped {
+                app.handle_paste(&typed);
+            }
+            for event in events {
+                match event {
+                    Event::Key(key) if key.kind == KeyEventKind::Press && !dropped => {
+                        app.handle_key_event(key, tx.clone());
+                    }
+                    Event::Paste(text) => app.handle_paste(&text),
+                    _ => {}
                 }
             }
+            // Keys and drops are what change the selection and settings
+            app.refresh_command_preview();
         }
 
         if app.should_quit {
@@ -112,7 +140,17 @@ fn main() -> Result<()> {
         }
     }
 
+    // Give the workers a moment to kill their ffmpeg processes and clean up
+    app.cancel_all();
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !app.active_jobs.is_empty() && Instant::now() < deadline {
+        if let Ok(event) = rx.recv_timeout(Duration::from_millis(100)) {
+            app.handle_ffmpeg_event(event, &tx);
+        }
+    }
+
     // Restore terminal
+    let _ = execute!(terminal.backend_mut(), DisableBracketedPaste);
     disable_raw_mode()?;
     execute!(
         terminal.backend_mut(),
@@ -0,0 +1,230 @@
+// Objective quality of a finished encode compared with its source: VMAF when
+// ffmpeg is built with libvmaf, SSIM and PSNR otherwise.
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, OnceLock};
+use std::thread;
+use crate::ffmpeg::{self, EncodeSettings, FfmpegEvent};
+use crate::probe;
+
+pub const METRICS_OFF: &str = "Off";
+pub const METRICS_ON: &str = "On";
+
+pub fn metrics_mode_choices() -> Vec<String> {
+    vec![METRICS_OFF.to_string(), METRICS_ON.to_string()]
+}
+
+#[derive(Clone, Default)]
+pub struct QualityScores {
+    pub vmaf: Option<f64>,
+    pub ssim: Option<f64>, // "All" channel, 0..1
+    pub psnr: Option<f64>, // average, dB
+}
+
+impl QualityScores {
+    pub fn is_empty(&self) -> bool {
+        self.vmaf.is_none() && self.ssim.is_none() && self.psnr.is_none()
+    }
+
+    // "VMAF 93.41 · SSIM 0.9862 · PSNR 41.20 dB"
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(vmaf) = self.vmaf {
+            parts.push(format!("VMAF {:.2}", vmaf));
+        }
+        if let Some(ssim) = self.ssim {
+            parts.push(format!("SSIM {:.4}", ssim));
+        }
+        if let Some(psnr) = self.psnr {
+            parts.push(format!("PSNR {:.2} dB", psnr));
+        }
+        parts.join(" · ")
+    }
+}
+
+// Asked once per run; the filter list doesn't change while we're running
+pub fn vmaf_available() -> bool {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+    *AVAILABLE.get_or_init(|| {
+        ffmpeg::ffmpeg_command()
+            .args(["-hide_banner", "-filters"])
+            .output()
+            .map(|out| String::from_utf8_lossy(&out.stdout).lines().any(|l| l.split_whitespace().nth(1) == Some("libvmaf")))
+            .unwrap_or(false)
+    })
+}
+
+// Filter graph comparing input 0 (distorted) with input 1 (reference). The encode is
+// scaled back to the source size and the source resampled to the encode's frame rate,
+// so frames line up one to one.
+pub fn metrics_filter(reference_size: Option<&str>, distorted_size: Option<&str>, fps: &str, use_vmaf: bool) -> String {
+    let mut distorted = "[0:v]setpts=PTS-STARTPTS".to_string();
+    if let Some(size) = reference_size
+        && distorted_size != Some(size)
+    {
+        distorted.push_str(&format!(",scale={}:flags=bicubic", size.replace('x', ":")));
+    }
+    distorted.push_str(",format=yuv420p");
+
+    let mut reference = "[1:v]".to_string();
+    if fps != "Same" {
+        reference.push_str(&format!("fps={},", fps));
+    }
+    reference.push_str("setpts=PTS-STARTPTS,format=yuv420p");
+
+    if use_vmaf {
+        let threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+        format!("{}[d];{}[r];[d][r]libvmaf=n_threads={}", distorted, reference, threads)
+    } else {
+        format!("{},split[d1][d2];{},split[r1][r2];[d1][r1]ssim;[d2][r2]psnr", distorted, reference)
+    }
+}
+
+// Picks the summary lines the metric filters print when they finish
+pub fn parse_scores(stderr: &str) -> QualityScores {
+    let value_after = |line: &str, key: &str| -> Option<f64> {
+        let start = line.find(key)? + key.len();
+        line[start..].split_whitespace().next()?.parse().ok()
+    };
+
+    let mut scores = QualityScores::default();
+    for line in stderr.lines() {
+        if line.contains("VMAF score:") {
+            scores.vmaf = value_after(line, "VMAF score:");
+        } else if line.contains("SSIM ") && line.contains("All:") {
+            scores.ssim = value_after(line, "All:");
+        } else if line.contains("PSNR ") && line.contains("average:") {
+            scores.psnr = value_after(line, "average:");
+        }
+    }
+    scores
+}
+
+pub fn measure_command(reference: &Path, distorted: &Path, filter: &str) -> Command {
+    let mut cmd = ffmpeg::ffmpeg_command();
+    cmd.args(["-nostdin", "-hide_banner", "-i"])
+        .arg(distorted)
+        .arg("-i")
+        .arg(reference)
+        .args(["-filter_complex", filter, "-f", "null", "-"]);
+    cmd
+}
+
+pub fn measure(reference: &Path, distorted: &Path, fps: &str, cancel: &AtomicBool) -> Result<QualityScores, String> {
+    let use_vmaf = vmaf_available();
+    let reference_size = probe::probe_resolution(reference);
+    let distorted_size = probe::probe_resolution(distorted);
+    let filter = metrics_filter(reference_size.as_deref(), distorted_size.as_deref(), fps, use_vmaf);
+    let (status, stderr) = ffmpeg::run_cancellable(measure_command(reference, distorted, &filter), cancel)?;
+
+    let scores = parse_scores(&stderr);
+    if !status.success() || scores.is_empty() {
+        let last = stderr.lines().rev().find(|l| !l.trim().is_empty()).unwrap_or("no scores reported");
+        return Err(format!("Quality measurement failed: {}", last));
+    }
+    Ok(scores)
+}
+
+pub fn start_measurement(
+    job: usize,
+    input: String,
+    output: String,
+    fps: String,
+    cancel: Arc<AtomicBool>,
+    tx: Sender<FfmpegEvent>,
+) {
+    thread::spawn(move || {
+        let metric = if vmaf_available() { "VMAF" } else { "SSIM/PSNR" };
+        let _ = tx.send(FfmpegEvent::Log(job, format!("Measuring quality ({})...", metric)));
+
+        let event = match measure(Path::new(&input), Path::new(&output), &fps, &cancel) {
+            Ok(scores) => FfmpegEvent::Measured(job, Some(scores)),
+            Err(e) if cancel.load(Ordering::SeqCst) => FfmpegEvent::Error(job, e),
+            Err(e) => {
+                // Scores are informational; the encode itself is still good
+                let _ = tx.send(FfmpegEvent::Log(job, e));
+                FfmpegEvent::Measured(job, None)
+            }
+        };
+        let _ = tx.send(event);
+    });
+}
+
+// movie.mp4 -> movie.mp4.report.txt
+pub fn report_path(output: &Path) -> PathBuf {
+    let mut name = output.file_name().unwrap_or_default().to_os_string();
+    name.push(".report.txt");
+    output.with_file_name(name)
+}
+
+// Plain key=value report stored next to the output
+pub fn write_report(output: &Path, input: &str, settings: &EncodeSettings, scores: &QualityScores) -> std::io::Result<PathBuf> {
+    let mut report = format!("input={}\noutput={}\nsettings={}\n", input, output.display(), settings.summary());
+    if let Some(vmaf) = scores.vmaf {
+        report.push_str(&format!("vmaf={:.4}\n", vmaf));
+    }
+    if let Some(ssim) = scores.ssim {
+        report.push_str(&format!("ssim={:.6}\n", ssim));
+    }
+    if let Some(psnr) = scores.psnr {
+        report.push_str(&format!("psnr={:.4}\n", psnr));
+    }
+
+    let path = report_path(output);
+    std::fs::write(&path, report)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The end of a measuring run with libvmaf
+    const VMAF_LOG: &str = "\
+frame=  250 fps= 41 q=-0.0 Lsize=N/A time=00:00:10.00 bitrate=N/A speed=1.63x
+[Parsed_libvmaf_4 @ 0x55d5c8a4e2c0] VMAF score: 93.412345
+";
+
+    // The same without libvmaf: SSIM and PSNR summaries
+    const SSIM_PSNR_LOG: &str = "\
+[Parsed_ssim_6 @ 0x5623a1f0b2c0] SSIM Y:0.987654 (19.083) U:0.991234 (20.570) V:0.990123 (20.049) All:0.988612 (19.435)
+[Parsed_psnr_7 @ 0x5623a1f0b2c0] PSNR y:41.234567 u:44.876543 v:45.012345 average:42.210987 min:35.123456 max:50.987654
+";
+
+    #[test]
+    fn vmaf_score() {
+        let scores = parse_scores(VMAF_LOG);
+        assert_eq!(scores.vmaf, Some(93.412345));
+        assert_eq!((scores.ssim, scores.psnr), (None, None));
+        assert_eq!(scores.summary(), "VMAF 93.41");
+    }
+
+    #[test]
+    fn ssim_and_psnr_summaries() {
+        let scores = parse_scores(SSIM_PSNR_LOG);
+        assert_eq!(scores.vmaf, None);
+        assert_eq!(scores.ssim, Some(0.988612));
+        assert_eq!(scores.psnr, Some(42.210987));
+        assert_eq!(scores.summary(), "SSIM 0.9886 · PSNR 42.21 dB");
+    }
+
+    #[test]
+    fn no_scores() {
+        assert!(parse_scores("Conversion failed!\n").is_empty());
+        // Per-frame lines without the summary keys don't count
+        assert!(parse_scores("[Parsed_psnr_7 @ 0x1] PSNR y:inf u:inf v:inf\n").is_empty());
+    }
+
+    #[test]
+    fn filter_lines_up_the_frames() {
+        assert_eq!(
+            metrics_filter(Some("1920x1080"), Some("1280x720"), "30", false),
+            "[0:v]setpts=PTS-STARTPTS,scale=1920:1080:flags=bicubic,format=yuv420p,split[d1][d2];\
+[1:v]fps=30,setpts=PTS-STARTPTS,format=yuv420p,split[r1][r2];[d1][r1]ssim;[d2][r2]psnr"
+        );
+        let vmaf = metrics_filter(Some("1920x1080"), Some("1920x1080"), "Same", true);
+        assert!(vmaf.starts_with("[0:v]setpts=PTS-STARTPTS,format=yuv420p[d];[1:v]setpts=PTS-STARTPTS,format=yuv420p[r];[d][r]libvmaf=n_threads="));
+    }
+}
@@ -0,0 +1,309 @@
+// Output file naming: templates such as `{dir}/{stem}_{encoder}_q{qp}.{container}`
+// rendered per queue entry, and detection of entries that would collide.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::fs::{File, TryLockError};
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::ffmpeg::EncodeSettings;
+
+pub const DEFAULT_TEMPLATE: &str = "{dir}/{stem}_encoded.{container}";
+
+pub const PLACEHOLDERS: &[&str] = &[
+    "dir", "stem", "ext", "encoder", "container", "qp", "fps", "audio", "pix_fmt", "resolution", "preset", "date", "time",
+];
+
+// Templates offered in the TUI; a custom one from the command line comes first
+pub fn template_choices(custom: Option<&str>) -> Vec<String> {
+    let mut choices: Vec<String> = custom.map(|t| vec![t.to_string()]).unwrap_or_default();
+    for template in [
+        DEFAULT_TEMPLATE,
+        "{dir}/{stem}_{encoder}_q{qp}.{container}",
+        "{dir}/{stem}_{resolution}.{container}",
+        "{dir}/{date}/{stem}.{container}",
+        "{dir}/{stem}_{preset}.{container}",
+    ] {
+        if !choices.iter().any(|c| c == template) {
+            choices.push(template.to_string());
+        }
+    }
+    choices
+}
+
+// Everything a template can refer to for one queue entry
+pub struct TemplateVars<'a> {
+    pub input: &'a Path,
+    pub dir: &'a Path, // where the output goes when the template uses {dir} or is relative
+    pub settings: &'a EncodeSettings,
+    pub resolution: Option<&'a str>,
+    pub preset: Option<&'a str>,
+}
+
+pub fn validate_template(template: &str) -> Result<(), String> {
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..].find('}').ok_or_else(|| format!("Unclosed '{{' in template: {}", template))?;
+        let name = &rest[start + 1..start + end];
+        if !PLACEHOLDERS.contains(&name) {
+            return Err(format!("Unknown placeholder {{{}}} (known: {})", name, PLACEHOLDERS.join(", ")));
+        }
+        rest = &rest[start + end + 1..];
+    }
+    if !template.contains("{container}") {
+        return Err("Template must contain {container} so the extension matches the format".to_string());
+    }
+    Ok(())
+}
+
+pub fn render(template: &str, vars: &TemplateVars) -> Result<PathBuf, String> {
+    validate_template(template)?;
+
+    let (date, time) = now_utc();
+    let fps = if vars.settings.fps == "Same" { "srcfps".to_string() } else { format!("{}fps", vars.settings.fps) };
+    let values: HashMap<&str, String> = HashMap::from([
+        ("dir", vars.dir.to_string_lossy().to_string()),
+        ("stem", vars.input.file_stem().unwrap_or_default().to_string_lossy().to_string()),
+        ("ext", vars.input.extension().unwrap_or_default().to_string_lossy().to_string()),
+        ("encoder", vars.settings.encoder.clone()),
+        ("container", vars.settings.container.clone()),
+        ("qp", vars.settings.qp.clone()),
+        ("fps", fps),
+        ("audio", vars.settings.audio_bitrate.clone()),
+        ("pix_fmt", vars.settings.pix_fmt.to_lowercase()),
+        ("resolution", vars.resolution.unwrap_or("unknown").to_string()),
+        ("preset", vars.preset.unwrap_or("custom").to_string()),
+        ("date", date),
+        ("time", time),
+    ]);
+
+    // One pass from left to right: braces inside a substituted value (a file named
+    // "clip{qp}", a preset name) are copied as they are, never expanded
+    let mut rendered = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = start + rest[start..].find('}').unwrap(); // checked by validate_template
+        rendered.push_str(&rest[..start]);
+        rendered.push_str(&values[&rest[start + 1..end]]);
+        rest = &rest[end + 1..];
+    }
+    rendered.push_str(rest);
+
+    // Relative templates are placed in the output directory
+    let path = PathBuf::from(rendered);
+    Ok(if path.is_relative() && !template.starts_with("{dir}") { vars.dir.join(path) } else { path })
+}
+
+// Comparable form of a path; Windows file names are case-insensitive
+pub fn normalize(path: &Path) -> String {
+    let text = path.to_string_lossy().replace('\\', "/");
+    if cfg!(windows) { text.to_lowercase() } else { text }
+}
+
+// For every output path, the positions (in `outputs`) of the other entries mapping to it
+pub fn find_collisions(outputs: &[PathBuf]) -> HashMap<usize, Vec<usize>> {
+    let mut by_path: HashMap<String, Vec<usize>> = HashMap::new();
+    for (i, path) in outputs.iter().enumerate() {
+        by_path.entry(normalize(path)).or_default().push(i);
+    }
+
+    let mut collisions = HashMap::new();
+    for group in by_path.values().filter(|g| g.len() > 1) {
+        for &i in group {
+            collisions.insert(i, group.iter().copied().filter(|&j| j != i).collect());
+        }
+    }
+    collisions
+}
+
+// Current UTC date and time as ("2024-05-01", "153012")
+pub fn now_utc() -> (String, String) {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (
+        format!("{:04}-{:02}-{:02}", year, month, day),
+        format!("{:02}{:02}{:02}", rem / 3600, (rem % 3600) / 60, rem % 60),
+    )
+}
+
+pub const OVERWRITE_ASK: &str = "Ask";
+pub const OVERWRITE_SKIP: &str = "Skip";
+pub const OVERWRITE_REPLACE: &str = "Overwrite";
+pub const OVERWRITE_RENAME: &str = "Rename";
+
+pub fn overwrite_policy_choices() -> Vec<String> {
+    vec![
+        OVERWRITE_ASK.to_string(),
+        OVERWRITE_SKIP.to_string(),
+        OVERWRITE_REPLACE.to_string(),
+        OVERWRITE_RENAME.to_string(),
+    ]
+}
+
+// First free "name_1.ext", "name_2.ext", ... next to `path`
+pub fn auto_rename(path: &Path) -> PathBuf {
+    let parent = path.parent().unwrap_or_else(|| Path::new(""));
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = path.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    (1..)
+        .map(|n| parent.join(format!("{}_{}{}", stem, n, ext)))
+        .find(|candidate| !candidate.exists())
+        .unwrap()
+}
+
+// True when both paths name the same file, even through different spellings
+pub fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => normalize(&absolute(a)) == normalize(&absolute(b)),
+    }
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+// Jobs write to a hidden temp file next to the final output and rename it into
+// place on success, so an interrupted encode never looks like a finished one
+const PARTIAL_MARKER: &str = ".partial";
+
+// movie.mp4 -> .movie.partial.mp4 (the extension stays so ffmpeg picks the muxer)
+pub fn partial_path(output: &Path) -> PathBuf {
+    let parent = output.parent().unwrap_or_else(|| Path::new(""));
+    let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = output.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    parent.join(format!(".{}{}{}", stem, PARTIAL_MARKER, ext))
+}
+
+// Held by a running job so other instances' startup cleanup leaves its temp files
+// alone. The OS drops the lock when the process dies, however it dies, so a lock
+// file that can be locked again belongs to a run that is gone.
+pub struct PartialLock {
+    path: PathBuf,
+    file: File,
+}
+
+impl PartialLock {
+    // None if the lock file can't be created; the job still runs, just unprotected
+    pub fn acquire(output: &Path) -> Option<Self> {
+        let path = lock_path(output);
+        let file = File::create(&path).ok()?;
+        file.try_lock().ok()?;
+        Some(Self { path, file })
+    }
+}
+
+impl Drop for PartialLock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+// movie.mp4 -> .movie.partial.lock, covering .movie.partial.mp4 and its chunk folder
+pub fn lock_path(output: &Path) -> PathBuf {
+    let parent = output.parent().unwrap_or_else(|| Path::new(""));
+    let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+    parent.join(format!(".{}{}.lock", stem, PARTIAL_MARKER))
+}
+
+// The lock guarding a temp file, chunk folder or lock file: .movie.partial.mp4,
+// .movie.partial_chunks and .movie.partial.lock all map to .movie.partial.lock
+fn owning_lock(path: &Path) -> Option<PathBuf> {
+    let name = path.file_name()?.to_string_lossy();
+    if !name.starts_with('.') {
+        return None;
+    }
+    let end = name.rfind(PARTIAL_MARKER)? + PARTIAL_MARKER.len();
+    let rest = &name[end..];
+    let temp = (rest.starts_with('.') && !rest[1..].contains('.')) || (rest == "_chunks" && path.is_dir());
+    temp.then(|| path.with_file_name(format!("{}.lock", &name[..end])))
+}
+
+fn is_locked(lock: &Path) -> bool {
+    let Ok(file) = File::open(lock) else { return false };
+    matches!(file.try_lock(), Err(TryLockError::WouldBlock))
+}
+
+// Deletes temp files and chunk folders left behind by a run that crashed or was
+// killed; returns how many
+pub fn remove_stale_partials(dir: &Path) -> usize {
+    let Ok(read_dir) = std::fs::read_dir(dir) else { return 0 };
+    let mut removed = 0;
+    for path in read_dir.flatten().map(|entry| entry.path()) {
+        let Some(lock) = owning_lock(&path) else { continue };
+        if is_locked(&lock) {
+            continue;
+        }
+        if path == lock {
+            let _ = std::fs::remove_file(&path);
+            continue;
+        }
+        let result = if path.is_dir() { std::fs::remove_dir_all(&path) } else { std::fs::remove_file(&path) };
+        if result.is_ok() {
+            removed += 1;
+        }
+    }
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> EncodeSettings {
+        EncodeSettings {
+            encoder: "libx265".to_string(),
+            container: "mkv".to_string(),
+            qp: "24".to_string(),
+            fps: "Same".to_string(),
+            audio_bitrate: "128k".to_string(),
+            pix_fmt: "Auto".to_string(),
+            profile: "Default".to_string(),
+            hw_decode: "Off".to_string(),
+            input_args: String::new(),
+            output_args: String::new(),
+            preset: Some("web {qp}".to_string()),
+            hw_device: None,
+        }
+    }
+
+    #[test]
+    fn placeholders_in_values_stay_literal() {
+        let settings = settings();
+        let vars = TemplateVars {
+            input: Path::new("/videos/{dir}/clip{qp}.mp4"),
+            dir: Path::new("/videos/{dir}"),
+            settings: &settings,
+            resolution: None,
+            preset: settings.preset.as_deref(),
+        };
+        let rendered = render("{dir}/{stem}_{encoder}_q{qp}_{preset}.{container}", &vars).unwrap();
+        assert_eq!(rendered, Path::new("/videos/{dir}/clip{qp}_libx265_q24_web {qp}.mkv"));
+    }
+
+    #[test]
+    fn relative_templates_go_into_the_output_directory() {
+        let settings = settings();
+        let vars = TemplateVars {
+            input: Path::new("/videos/clip.mp4"),
+            dir: Path::new("/out"),
+            settings: &settings,
+            resolution: Some("1920x1080"),
+            preset: None,
+        };
+        assert_eq!(render("{stem}_{resolution}.{container}", &vars).unwrap(), Path::new("/out/clip_1920x1080.mkv"));
+    }
+}
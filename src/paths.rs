@@ -0,0 +1,145 @@
+// Turning pasted or dropped text into file paths.
+// Terminals deliver drag & drop as text: quoted ('...' or "..."), with
+// backslash-escaped spaces, or as file:// URIs, one or more per paste.
+
+use std::path::Path;
+
+pub const VIDEO_EXTENSIONS: &[&str] = &[
+    "mp4", "mkv", "avi", "mov", "webm", "m4v", "wmv", "flv", "ts", "m2ts", "mts", "mpg", "mpeg", "3gp", "ogv", "gif",
+];
+
+// Characters a shell-style drop escapes with a backslash. Other backslashes are
+// kept as-is so Windows paths like C:\Videos\a.mp4 survive.
+const ESCAPABLE: &[char] = &[' ', '\'', '"', '(', ')', '[', ']', '&', ';', '$', '!', '#'];
+
+pub fn is_video_file(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| VIDEO_EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+pub fn parse_pasted_paths(text: &str) -> Vec<String> {
+    let mut paths = Vec::new();
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+    let mut has_token = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match quote {
+            Some(q) if ch == q => quote = None,
+            Some(_) => current.push(ch),
+            None => match ch {
+                '\'' | '"' => {
+                    quote = Some(ch);
+                    has_token = true;
+                }
+                '\\' if chars.peek().is_some_and(|next| ESCAPABLE.contains(next)) => {
+                    current.push(chars.next().unwrap());
+                    has_token = true;
+                }
+                c if c.is_whitespace() => {
+                    if has_token {
+                        paths.push(std::mem::take(&mut current));
+                        has_token = false;
+                    }
+                }
+                c => {
+                    current.push(c);
+                    has_token = true;
+                }
+            },
+        }
+    }
+    if has_token {
+        paths.push(current);
+    }
+
+    paths.into_iter().map(|p| decode_file_uri(&p)).filter(|p| !p.is_empty()).collect()
+}
+
+// Text typed out by a drop: one or more paths, all of which exist
+pub fn names_existing_files(text: &str) -> bool {
+    let paths = parse_pasted_paths(text);
+    !paths.is_empty() && paths.iter().all(|path| Path::new(path).exists())
+}
+
+// file:///home/me/a%20b.mp4 -> /home/me/a b.mp4, file:///C:/a.mp4 -> C:/a.mp4
+fn decode_file_uri(path: &str) -> String {
+    let Some(rest) = path.strip_prefix("file://") else {
+        return path.to_string();
+    };
+    let rest = rest.strip_prefix("localhost").unwrap_or(rest);
+    let decoded = percent_decode(rest);
+
+    // Drop the leading slash before a Windows drive letter
+    let bytes = decoded.as_bytes();
+    if bytes.len() >= 3 && bytes[0] == b'/' && bytes[1].is_ascii_alphabetic() && bytes[2] == b':' {
+        decoded[1..].to_string()
+    } else {
+        decoded
+    }
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && i + 2 < bytes.len()
+            && let Some(byte) = std::str::from_utf8(&bytes[i + 1..i + 3])
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            out.push(byte);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quoted_paths_keep_their_spaces() {
+        assert_eq!(parse_pasted_paths("'/videos/my clip.mp4' \"/videos/other one.mkv\""), ["/videos/my clip.mp4", "/videos/other one.mkv"]);
+        assert_eq!(parse_pasted_paths("  /a.mp4\n/b.mp4\t"), ["/a.mp4", "/b.mp4"]);
+        assert!(parse_pasted_paths(" \n ").is_empty());
+    }
+
+    #[test]
+    fn backslash_escapes() {
+        assert_eq!(parse_pasted_paths(r"/videos/my\ clip\ \(1\).mp4 /b.mp4"), ["/videos/my clip (1).mp4", "/b.mp4"]);
+        assert_eq!(parse_pasted_paths(r"/videos/it\'s.mp4"), ["/videos/it's.mp4"]);
+    }
+
+    #[test]
+    fn file_uris() {
+        assert_eq!(
+            parse_pasted_paths("file:///home/me/a%20b.mp4 file://localhost/home/me/c.mkv file:///home/me/%C3%A9t%C3%A9.mov"),
+            ["/home/me/a b.mp4", "/home/me/c.mkv", "/home/me/été.mov"]
+        );
+        assert_eq!(parse_pasted_paths("file:///C:/Videos/a%20b.mp4"), ["C:/Videos/a b.mp4"]);
+        // Only URIs are percent-decoded; a stray % stays
+        assert_eq!(parse_pasted_paths("/videos/100%20.mp4 file:///x/50%.mp4"), ["/videos/100%20.mp4", "/x/50%.mp4"]);
+    }
+
+    #[test]
+    fn windows_paths_keep_their_backslashes() {
+        assert_eq!(parse_pasted_paths(r"C:\Videos\a.mp4"), [r"C:\Videos\a.mp4"]);
+        assert_eq!(parse_pasted_paths(r#""C:\My Videos\a b.mp4" D:\b.mkv"#), [r"C:\My Videos\a b.mp4", r"D:\b.mkv"]);
+    }
+
+    #[test]
+    fn video_extensions() {
+        assert!(is_video_file(Path::new("/a/B.MKV")));
+        assert!(!is_video_file(Path::new("/a/b.txt")));
+        assert!(!is_video_file(Path::new("/a/mp4")));
+    }
+}
@@ -0,0 +1,187 @@
+// Named encode settings. A few are built in; the user's own live in presets.toml
+// next to config.toml, and single presets can be shared as *.preset.toml files.
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::codecs;
+use crate::config;
+use crate::ffmpeg::EncodeSettings;
+use crate::hwaccel;
+
+pub const PRESET_FILE_SUFFIX: &str = ".preset.toml";
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Preset {
+    pub name: String,
+    pub encoder: String,
+    pub container: String,
+    #[serde(deserialize_with = "config::required_string_or_number")]
+    pub qp: String,
+    #[serde(deserialize_with = "config::required_string_or_number")]
+    pub fps: String,
+    pub audio_bitrate: String,
+    pub pix_fmt: String,
+    #[serde(default = "default_profile", skip_serializing_if = "is_default_profile")]
+    pub profile: String,
+    #[serde(default = "default_hw_decode", skip_serializing_if = "is_default_hw_decode")]
+    pub hw_decode: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub input_args: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub output_args: String,
+}
+
+impl Preset {
+    pub fn from_settings(name: &str, settings: &EncodeSettings) -> Self {
+        Self {
+            name: name.to_string(),
+            encoder: settings.encoder.clone(),
+            container: settings.container.clone(),
+            qp: settings.qp.clone(),
+            fps: settings.fps.clone(),
+            audio_bitrate: settings.audio_bitrate.clone(),
+            pix_fmt: settings.pix_fmt.clone(),
+            profile: settings.profile.clone(),
+            hw_decode: settings.hw_decode.clone(),
+            input_args: settings.input_args.clone(),
+            output_args: settings.output_args.clone(),
+        }
+    }
+
+    pub fn settings(&self) -> EncodeSettings {
+        EncodeSettings {
+            encoder: self.encoder.clone(),
+            container: self.container.clone(),
+            qp: self.qp.clone(),
+            fps: self.fps.clone(),
+            audio_bitrate: self.audio_bitrate.clone(),
+            pix_fmt: self.pix_fmt.clone(),
+            profile: self.profile.clone(),
+            hw_decode: self.hw_decode.clone(),
+            input_args: self.input_args.clone(),
+            output_args: self.output_args.clone(),
+            preset: Some(self.name.clone()),
+            hw_device: None,
+        }
+    }
+
+    // Same encode settings, whatever they are called
+    pub fn matches(&self, settings: &EncodeSettings) -> bool {
+        self.encoder == settings.encoder
+            && self.container == settings.container
+            && self.qp == settings.qp
+            && self.fps == settings.fps
+            && self.audio_bitrate == settings.audio_bitrate
+            && self.pix_fmt == settings.pix_fmt
+            && self.profile == settings.profile
+            && self.hw_decode == settings.hw_decode
+            && self.input_args == settings.input_args
+            && self.output_args == settings.output_args
+    }
+}
+
+fn default_profile() -> String {
+    codecs::PROFILE_DEFAULT.to_string()
+}
+
+fn is_default_profile(profile: &str) -> bool {
+    profile == codecs::PROFILE_DEFAULT
+}
+
+fn default_hw_decode() -> String {
+    hwaccel::DECODE_OFF.to_string()
+}
+
+fn is_default_hw_decode(hw_decode: &str) -> bool {
+    hw_decode == hwaccel::DECODE_OFF
+}
+
+pub fn built_in() -> Vec<Preset> {
+    let preset = |name: &str, encoder: &str, container: &str, qp: &str, fps: &str, audio: &str, pix_fmt: &str| Preset {
+        name: name.to_string(),
+        encoder: encoder.to_string(),
+        container: container.to_string(),
+        qp: qp.to_string(),
+        fps: fps.to_string(),
+        audio_bitrate: audio.to_string(),
+        pix_fmt: pix_fmt.to_string(),
+        profile: codecs::PROFILE_DEFAULT.to_string(),
+        hw_decode: hwaccel::DECODE_OFF.to_string(),
+        input_args: String::new(),
+        output_args: String::new(),
+    };
+    vec![
+        preset("Discord 8MB", "libx264", "mp4", "32", "30", "128k", "Auto"),
+        preset("YouTube upload", "libx264", "mp4", "18", "Same", "320k", "Auto"),
+        preset("Archive x265 10-bit", "libx265", "mkv", "18", "Same", "256k", "yuv420p10le"),
+        preset("NVENC fast", "h264_nvenc", "mp4", "23", "Same", "192k", "Auto"),
+    ]
+}
+
+// The file holding the user's presets as [[preset]] tables
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct PresetList {
+    preset: Vec<Preset>,
+}
+
+pub fn presets_path() -> Option<PathBuf> {
+    config::config_dir().map(|dir| dir.join("presets.toml"))
+}
+
+pub fn load_user_presets() -> Result<Vec<Preset>, String> {
+    let Some(path) = presets_path() else {
+        return Ok(Vec::new());
+    };
+    match std::fs::read_to_string(&path) {
+        Ok(text) => config::parse_toml::<PresetList>(&path, &text).map(|list| list.preset),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(format!("Cannot read {}: {}", path.display(), e)),
+    }
+}
+
+pub fn save_user_presets(presets: &[Preset]) -> Result<(), String> {
+    let path = presets_path().ok_or("No config directory on this system")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+    }
+    let list = PresetList { preset: presets.to_vec() };
+    let text = toml::to_string_pretty(&list).map_err(|e| format!("Cannot serialize presets: {}", e))?;
+    std::fs::write(&path, text).map_err(|e| format!("Cannot write {}: {}", path.display(), e))
+}
+
+pub fn is_preset_file(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| name.to_string_lossy().to_lowercase().ends_with(PRESET_FILE_SUFFIX))
+}
+
+// "Archive x265 10-bit" -> <dir>/archive_x265_10-bit.preset.toml
+pub fn export(preset: &Preset, dir: &Path) -> Result<PathBuf, String> {
+    let slug: String = preset
+        .name
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' { c.to_ascii_lowercase() } else { '_' })
+        .collect();
+    let path = dir.join(format!("{}{}", slug, PRESET_FILE_SUFFIX));
+    let text = toml::to_string_pretty(preset).map_err(|e| format!("Cannot serialize preset: {}", e))?;
+    std::fs::write(&path, text).map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+pub fn import(path: &Path) -> Result<Preset, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    config::parse_toml(path, &text)
+}
+
+// Names end up in file names through {preset}, so keep them path-safe
+pub fn validate_name(name: &str, existing: &[Preset]) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("Preset name can't be empty".to_string());
+    }
+    if name.contains(['/', '\\']) {
+        return Err("Preset name can't contain / or \\".to_string());
+    }
+    if existing.iter().any(|p| p.name.eq_ignore_ascii_case(name)) {
+        return Err(format!("A preset named '{}' already exists", name));
+    }
+    Ok(())
+}
@@ -0,0 +1,93 @@
+// Shell-ready command lines of what a queue entry will run, for the preview panel,
+// the clipboard (OSC 52) and exported scripts
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+pub struct JobCommand {
+    pub label: String,
+    pub line: String, // a shell command, or empty for a plain note
+}
+
+impl JobCommand {
+    pub fn new(label: &str, cmd: &Command) -> Self {
+        Self { label: label.to_string(), line: command_line(cmd) }
+    }
+
+    pub fn shell(label: &str, line: String) -> Self {
+        Self { label: label.to_string(), line }
+    }
+
+    pub fn note(label: String) -> Self {
+        Self { label, line: String::new() }
+    }
+}
+
+pub fn quote(arg: &str) -> String {
+    // Only fails on NUL bytes, which can't be in an argument anyway
+    shlex::try_quote(arg).map(|q| q.into_owned()).unwrap_or_else(|_| arg.to_string())
+}
+
+pub fn command_line(cmd: &Command) -> String {
+    std::iter::once(cmd.get_program())
+        .chain(cmd.get_args())
+        .map(|arg| quote(&arg.to_string_lossy()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Labels become comments, so the text pastes straight into a shell
+pub fn script(commands: &[JobCommand]) -> String {
+    let mut text = String::from("#!/bin/sh\nset -e\n");
+    for command in commands {
+        text.push_str(&format!("\n# {}\n", command.label));
+        if !command.line.is_empty() {
+            text.push_str(&command.line);
+            text.push('\n');
+        }
+    }
+    text
+}
+
+// movie.mp4 -> movie.mp4.sh
+pub fn script_path(output: &Path) -> PathBuf {
+    let mut name = output.file_name().unwrap_or_default().to_os_string();
+    name.push(".sh");
+    output.with_file_name(name)
+}
+
+pub fn write_script(output: &Path, commands: &[JobCommand]) -> std::io::Result<PathBuf> {
+    let path = script_path(output);
+    std::fs::write(&path, script(commands))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(path)
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+// Asks the terminal to put `text` on the system clipboard. Works over SSH too, as
+// long as the terminal supports OSC 52 (most do; some need it enabled).
+pub fn copy_to_clipboard(text: &str) -> std::io::Result<()> {
+    let mut stdout = std::io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+    stdout.flush()
+}
@@ -0,0 +1,45 @@
+// Small ffprobe helpers for information about input files
+use std::path::Path;
+use std::process::Command;
+use crate::ffmpeg;
+
+pub fn probe_duration(path: &Path) -> Option<f64> {
+    let output = ffmpeg::ffprobe_command()
+        .args(["-v", "error", "-show_entries", "format=duration", "-of", "csv=p=0"])
+        .arg(path)
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+}
+
+// 3725.4 -> "01:02:05"
+pub fn format_duration(seconds: f64) -> String {
+    let total = seconds.max(0.0).round() as u64;
+    format!("{:02}:{:02}:{:02}", total / 3600, (total % 3600) / 60, total % 60)
+}
+
+// 1536000 -> "1.5 MB"
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 { format!("{} {}", bytes, UNITS[0]) } else { format!("{:.1} {}", size, UNITS[unit]) }
+}
+
+pub fn resolution_command(path: &Path) -> Command {
+    let mut cmd = ffmpeg::ffprobe_command();
+    cmd.args(["-v", "error", "-select_streams", "v:0", "-show_entries", "stream=width,height", "-of", "csv=s=x:p=0"])
+        .arg(path);
+    cmd
+}
+
+// Width x height of the first video stream, e.g. "1920x1080"
+pub fn probe_resolution(path: &Path) -> Option<String> {
+    let output = resolution_command(path).output().ok()?;
+    let text = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if text.contains('x') { Some(text) } else { None }
+}
@@ -0,0 +1,126 @@
+use std::path::{Path, PathBuf};
+use crate::ffmpeg::EncodeSettings;
+use crate::diagnostics::Diagnosis;
+use crate::logs::LogFile;
+use crate::metrics::QualityScores;
+use crate::retry::Attempt;
+
+#[derive(Clone, PartialEq)]
+pub enum JobStatus {
+    Pending,
+    Searching,
+    Encoding,
+    Verifying,
+    Measuring,
+    Done,
+    Failed(String),
+    VerifyFailed(String),
+    Skipped,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            JobStatus::Pending => "pending",
+            JobStatus::Searching => "searching quality",
+            JobStatus::Encoding => "encoding",
+            JobStatus::Verifying => "verifying",
+            JobStatus::Measuring => "measuring quality",
+            JobStatus::Done => "done",
+            JobStatus::Failed(_) => "failed",
+            JobStatus::VerifyFailed(_) => "failed verification",
+            JobStatus::Skipped => "skipped",
+            JobStatus::Cancelled => "cancelled",
+        }
+    }
+
+    // Finished one way or another; nothing more will happen unless retried
+    pub fn is_finished(&self) -> bool {
+        !matches!(self, JobStatus::Pending | JobStatus::Searching | JobStatus::Encoding | JobStatus::Verifying | JobStatus::Measuring)
+    }
+}
+
+// One file in the batch, with the settings it was queued (or later edited) with
+pub struct QueueItem {
+    pub id: usize, // stable across reordering; ffmpeg events refer to it
+    pub input: String,
+    pub output_dir: Option<PathBuf>, // set when mirroring a directory tree
+    pub settings: EncodeSettings,
+    pub status: JobStatus,
+    pub resolution: Option<String>, // probed on demand for the {resolution} placeholder
+    pub overwrite_answer: Option<String>, // what the user chose when asked about an existing output
+    pub metrics: Option<QualityScores>,
+    pub target_qp: Option<String>, // found by the target-quality search; reused on retry
+    pub attempts: Vec<Attempt>,    // failed runs, oldest first
+    pub retries_used: usize,       // automatic retries with the current settings
+    pub output: Vec<String>,       // everything ffmpeg printed during the latest run
+    pub diagnosis: Option<Diagnosis>, // why the latest run failed, when recognised
+    pub log: Vec<String>,             // every run's output and progress messages, for the log viewer
+    pub log_file: Option<LogFile>,    // opened when the entry first runs
+}
+
+impl QueueItem {
+    pub fn new(id: usize, input: String, settings: EncodeSettings, output_dir: Option<PathBuf>) -> Self {
+        Self {
+            id,
+            input,
+            output_dir,
+            settings,
+            status: JobStatus::Pending,
+            resolution: None,
+            overwrite_answer: None,
+            metrics: None,
+            target_qp: None,
+            attempts: Vec::new(),
+            retries_used: 0,
+            output: Vec::new(),
+            diagnosis: None,
+            log: Vec::new(),
+            log_file: None,
+        }
+    }
+
+    // Same file and settings under a new id, ready to run again
+    pub fn duplicate(&self, id: usize) -> Self {
+        Self {
+            id,
+            input: self.input.clone(),
+            output_dir: self.output_dir.clone(),
+            settings: self.settings.clone(),
+            status: JobStatus::Pending,
+            resolution: self.resolution.clone(),
+            overwrite_answer: None,
+            metrics: None,
+            target_qp: None,
+            attempts: Vec::new(),
+            retries_used: 0,
+            output: Vec::new(),
+            diagnosis: None,
+            log: Vec::new(),
+            log_file: None,
+        }
+    }
+
+    // Forgets the previous run's output before the entry runs again
+    pub fn start_run(&mut self, status: JobStatus) {
+        self.status = status;
+        self.output.clear();
+        self.diagnosis = None;
+    }
+
+    // Adds a line to the entry's log and its log file
+    pub fn write_log(&mut self, line: String) {
+        if let Some(file) = self.log_file.as_mut() {
+            file.write(&line);
+        }
+        self.log.push(line);
+    }
+
+    pub fn file_name(&self) -> String {
+        Path::new(&self.input)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| self.input.clone())
+    }
+}
@@ -0,0 +1,61 @@
+// What happens after a job fails: the same settings are tried again a few times,
+// then, with the fallback enabled, the parts most likely to be at fault are
+// swapped out one at a time: hardware decoding first, then the hardware encoder.
+use crate::codecs;
+use crate::ffmpeg::EncodeSettings;
+use crate::hwaccel;
+use crate::hwcaps;
+
+pub const FALLBACK_OFF: &str = "Off";
+pub const FALLBACK_SOFTWARE: &str = "Software";
+
+pub fn retry_choices() -> Vec<String> {
+    ["0", "1", "2", "3"].map(String::from).to_vec()
+}
+
+pub fn fallback_choices() -> Vec<String> {
+    vec![FALLBACK_OFF.to_string(), FALLBACK_SOFTWARE.to_string()]
+}
+
+// One failed run of a queue entry
+#[derive(Clone)]
+pub struct Attempt {
+    pub summary: String, // settings it ran with
+    pub error: String,
+}
+
+// Software encoder producing the same format as a hardware one
+pub fn software_encoder(encoder: &str) -> Option<&'static str> {
+    if !hwcaps::is_hardware(encoder) {
+        return None;
+    }
+    match encoder.split('_').next() {
+        Some("h264") => Some("libx264"),
+        Some("hevc") => Some("libx265"),
+        Some("av1") => Some("libsvtav1"),
+        _ => None,
+    }
+}
+
+// The next step of the fallback chain and a description of the change, or None
+// once there is nothing left to swap
+pub fn fallback(settings: &EncodeSettings) -> Option<(EncodeSettings, String)> {
+    if settings.hw_decode != hwaccel::DECODE_OFF {
+        let next = EncodeSettings { hw_decode: hwaccel::DECODE_OFF.to_string(), preset: None, ..settings.clone() };
+        return Some((next, format!("{} decoding -> software decoding", settings.hw_decode)));
+    }
+    let encoder = software_encoder(&settings.encoder)?;
+    // Keep the pixel format when the software encoder can produce it
+    let pix_fmt = match codecs::resolve_pix_fmt(encoder, codecs::PROFILE_DEFAULT, &settings.pix_fmt) {
+        Ok(_) => settings.pix_fmt.clone(),
+        Err(_) => codecs::PIX_FMT_AUTO.to_string(),
+    };
+    let next = EncodeSettings {
+        encoder: encoder.to_string(),
+        profile: codecs::PROFILE_DEFAULT.to_string(),
+        pix_fmt,
+        preset: None,
+        ..settings.clone()
+    };
+    Some((next, format!("{} -> {}", settings.encoder, encoder)))
+}
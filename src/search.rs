@@ -0,0 +1,284 @@
+// Target-quality mode: short samples of the input are encoded at a few QP values
+// and scored with VMAF, narrowing in on the highest QP (smallest file) that still
+// reaches the target. The full encode then runs with that QP.
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::AtomicBool;
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::thread;
+use crate::extra_args;
+use crate::ffmpeg::{self, EncodeSettings, FfmpegEvent};
+use crate::metrics;
+use crate::probe;
+
+pub const TARGET_OFF: &str = "Off";
+
+const SAMPLE_COUNT: usize = 3;
+const SAMPLE_SECONDS: f64 = 4.0;
+const MAX_STEPS: usize = 6;
+
+// QP values the search may pick from; below 10 files get huge for no visible gain
+pub const MIN_QP: u32 = 10;
+pub const MAX_QP: u32 = 51;
+
+pub fn target_choices() -> Vec<String> {
+    let mut choices = vec![TARGET_OFF.to_string()];
+    choices.extend(["80", "85", "88", "90", "93", "95", "97"].iter().map(|t| t.to_string()));
+    choices
+}
+
+// Next QP to try, or None when the search has converged. `good` is the highest QP
+// known to reach the target and `bad` the lowest known to miss it, with their scores.
+pub fn next_qp(good: Option<(u32, f64)>, bad: Option<(u32, f64)>, target: f64) -> Option<u32> {
+    match (good, bad) {
+        (Some((good_qp, good_score)), Some((bad_qp, bad_score))) => {
+            if bad_qp <= good_qp + 1 {
+                return None;
+            }
+            // Scores fall roughly linearly with QP over a short range
+            let ratio = if good_score > bad_score { (good_score - target) / (good_score - bad_score) } else { 0.5 };
+            let guess = good_qp as f64 + ratio * (bad_qp - good_qp) as f64;
+            Some((guess.round() as u32).clamp(good_qp + 1, bad_qp - 1))
+        }
+        (Some((good_qp, _)), None) => (good_qp < MAX_QP).then(|| (good_qp + MAX_QP).div_ceil(2)),
+        (None, Some((bad_qp, _))) => (bad_qp > MIN_QP).then(|| (MIN_QP + bad_qp) / 2),
+        (None, None) => Some((MIN_QP + MAX_QP) / 2),
+    }
+}
+
+// Where the samples are cut: evenly spread, or the whole file when it's short
+fn sample_starts(duration: Option<f64>) -> Vec<Option<f64>> {
+    match duration {
+        Some(d) if d > SAMPLE_SECONDS * SAMPLE_COUNT as f64 * 2.0 => {
+            (1..=SAMPLE_COUNT).map(|i| Some(d * i as f64 / (SAMPLE_COUNT + 1) as f64)).collect()
+        }
+        _ => vec![None],
+    }
+}
+
+fn cut_args(start: Option<f64>) -> Vec<String> {
+    match start {
+        Some(s) => vec!["-ss".to_string(), format!("{:.3}", s)],
+        None => Vec::new(),
+    }
+}
+
+fn run(cmd: Command, cancel: &AtomicBool) -> Result<(), String> {
+    let (status, stderr) = ffmpeg::run_cancellable(cmd, cancel)?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(stderr.lines().rev().find(|l| !l.trim().is_empty()).unwrap_or("ffmpeg failed").to_string())
+    }
+}
+
+// Lossless copies of the sample ranges, so every candidate is scored against the same frames
+fn extract_references(input: &str, starts: &[Option<f64>], work_dir: &Path, cancel: &AtomicBool) -> Result<Vec<PathBuf>, String> {
+    let mut references = Vec::new();
+    for (i, start) in starts.iter().enumerate() {
+        let path = work_dir.join(format!("reference_{}.mkv", i));
+        let mut cmd = ffmpeg::ffmpeg_command();
+        cmd.args(["-nostdin", "-y"]).args(cut_args(*start)).arg("-i").arg(input);
+        if start.is_some() {
+            cmd.args(["-t", &SAMPLE_SECONDS.to_string()]);
+        }
+        cmd.args(["-map", "0:v:0", "-an", "-c:v", "ffv1"]).arg(&path);
+        run(cmd, cancel).map_err(|e| format!("Cannot cut sample: {}", e))?;
+        references.push(path);
+    }
+    Ok(references)
+}
+
+// Mean VMAF of the samples encoded at `qp`
+#[allow(clippy::too_many_arguments)]
+fn score_qp(
+    qp: u32,
+    input: &str,
+    settings: &EncodeSettings,
+    threads: &Option<String>,
+    starts: &[Option<f64>],
+    references: &[PathBuf],
+    work_dir: &Path,
+    cancel: &AtomicBool,
+) -> Result<f64, String> {
+    let settings = EncodeSettings { qp: qp.to_string(), ..settings.clone() };
+    let (input_args, output_args) = ffmpeg::encode_args(&settings, threads)?;
+
+    let mut total = 0.0;
+    for (i, (start, reference)) in starts.iter().zip(references).enumerate() {
+        let sample = work_dir.join(format!("sample_{}_q{}.mkv", i, qp));
+        let mut cmd = ffmpeg::ffmpeg_command();
+        cmd.args(["-nostdin", "-y"]).args(&input_args).args(cut_args(*start)).arg("-i").arg(input);
+        if start.is_some() {
+            cmd.args(["-t", &SAMPLE_SECONDS.to_string()]);
+        }
+        cmd.args(["-map", "0:v:0", "-an"]).args(&output_args).arg(&sample);
+        run(cmd, cancel).map_err(|e| format!("Sample encode at QP {} failed: {}", qp, e))?;
+
+        let scores = metrics::measure(reference, &sample, &settings.fps, cancel)?;
+        total += scores.vmaf.ok_or("VMAF score missing from libvmaf output")?;
+        let _ = std::fs::remove_file(&sample);
+    }
+    Ok(total / starts.len() as f64)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn search(
+    job: usize,
+    input: &str,
+    settings: &EncodeSettings,
+    target: f64,
+    threads: &Option<String>,
+    cancel: &AtomicBool,
+    work_dir: &Path,
+    tx: &Sender<FfmpegEvent>,
+) -> Result<u32, String> {
+    let (input_args, output_args) = extra_args::for_settings(settings)?;
+    extra_args::check_untrimmed(&input_args, "a target VMAF")?;
+    extra_args::check_untrimmed(&output_args, "a target VMAF")?;
+
+    std::fs::create_dir_all(work_dir).map_err(|e| format!("Cannot create {}: {}", work_dir.display(), e))?;
+    let starts = sample_starts(probe::probe_duration(Path::new(input)));
+    let references = extract_references(input, &starts, work_dir, cancel)?;
+
+    // Start from the QP the entry was queued with; it's usually a sensible guess
+    let mut qp = settings.qp.parse::<u32>().unwrap_or(MAX_QP).clamp(MIN_QP, MAX_QP);
+    let mut good: Option<(u32, f64)> = None;
+    let mut bad: Option<(u32, f64)> = None;
+
+    for step in 1..=MAX_STEPS {
+        let _ = tx.send(FfmpegEvent::Progress(
+            job,
+            (step - 1) as f64 / MAX_STEPS as f64,
+            "-".to_string(),
+            "-".to_string(),
+            "-".to_string(),
+            format!("step {}/{}", step, MAX_STEPS),
+        ));
+        let score = score_qp(qp, input, settings, threads, &starts, &references, work_dir, cancel)?;
+        let reached = score >= target;
+        let _ = tx.send(FfmpegEvent::Log(
+            job,
+            format!("Search step {}: QP {} -> VMAF {:.2} ({} target {})", step, qp, score, if reached { "meets" } else { "below" }, target),
+        ));
+
+        if reached {
+            good = Some((qp, score));
+        } else {
+            bad = Some((qp, score));
+        }
+        match next_qp(good, bad, target) {
+            Some(next) => qp = next,
+            None => break,
+        }
+    }
+
+    // If even the best quality tried misses the target, use that best quality
+    Ok(good.map(|(qp, _)| qp).unwrap_or_else(|| bad.map(|(qp, _)| qp).unwrap_or(MIN_QP)))
+}
+
+pub fn start_search(
+    job: usize,
+    input: String,
+    settings: EncodeSettings,
+    target: f64,
+    threads: Option<String>,
+    cancel: Arc<AtomicBool>,
+    tx: Sender<FfmpegEvent>,
+) {
+    thread::spawn(move || {
+        let _ = tx.send(FfmpegEvent::Log(job, format!("Searching for the QP that reaches VMAF {}...", target)));
+        let settings = ffmpeg::with_working_decoder(job, &input, &settings, &cancel, &tx);
+        let work_dir = std::env::temp_dir().join(format!("videoencoder_search_{}_{}", std::process::id(), job));
+        let result = search(job, &input, &settings, target, &threads, &cancel, &work_dir, &tx);
+        let _ = std::fs::remove_dir_all(&work_dir);
+
+        let event = match result {
+            Ok(qp) => FfmpegEvent::QualityFound(job, qp.to_string()),
+            Err(e) => FfmpegEvent::Error(job, e),
+        };
+        let _ = tx.send(event);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Runs the search against a made-up score curve; returns the QPs tried and the pick
+    fn run_search(start: u32, target: f64, score: impl Fn(u32) -> f64) -> (Vec<u32>, u32) {
+        let (mut good, mut bad) = (None, None);
+        let mut qp = start;
+        let mut tried = Vec::new();
+        for _ in 0..MAX_STEPS {
+            tried.push(qp);
+            if score(qp) >= target {
+                good = Some((qp, score(qp)));
+            } else {
+                bad = Some((qp, score(qp)));
+            }
+            match next_qp(good, bad, target) {
+                Some(next) => qp = next,
+                None => break,
+            }
+        }
+        (tried, good.or(bad).map(|(qp, _)| qp).unwrap())
+    }
+
+    #[test]
+    fn starts_in_the_middle() {
+        assert_eq!(next_qp(None, None, 93.0), Some(30));
+    }
+
+    #[test]
+    fn one_sided_steps_halve_towards_the_bounds() {
+        assert_eq!(next_qp(Some((30, 96.0)), None, 93.0), Some(41));
+        assert_eq!(next_qp(None, Some((30, 88.0)), 93.0), Some(20));
+    }
+
+    #[test]
+    fn target_met_or_missed_at_the_bounds() {
+        // Even the highest QP reaches the target: nothing left to try
+        assert_eq!(next_qp(Some((MAX_QP, 94.0)), None, 93.0), None);
+        // Even the lowest QP misses it
+        assert_eq!(next_qp(None, Some((MIN_QP, 90.0)), 93.0), None);
+        assert_eq!(run_search(30, 50.0, |_| 99.0), (vec![30, 41, 46, 49, 50, 51], 51));
+        assert_eq!(run_search(30, 99.5, |_| 90.0).1, MIN_QP);
+    }
+
+    #[test]
+    fn interpolates_between_good_and_bad() {
+        // 93 lies 40% of the way from 95 down to 90
+        assert_eq!(next_qp(Some((20, 95.0)), Some((30, 90.0)), 93.0), Some(24));
+        // Converged once the two are neighbours
+        assert_eq!(next_qp(Some((24, 93.2)), Some((25, 92.8)), 93.0), None);
+    }
+
+    #[test]
+    fn guesses_stay_strictly_between() {
+        // Scores barely apart would put the guess far outside the bracket
+        assert_eq!(next_qp(Some((20, 99.0)), Some((40, 98.9)), 93.0), Some(39));
+        assert_eq!(next_qp(Some((20, 93.0)), Some((40, 10.0)), 93.0), Some(21));
+    }
+
+    #[test]
+    fn non_monotonic_scores_bisect() {
+        // A sample scoring higher at the bad QP than the good one can't be interpolated
+        assert_eq!(next_qp(Some((20, 93.5)), Some((30, 94.0)), 93.0), Some(25));
+    }
+
+    #[test]
+    fn finds_the_highest_qp_reaching_the_target() {
+        let linear = |qp: u32| 100.0 - 0.5 * qp as f64;
+        let (tried, picked) = run_search(30, 90.0, linear);
+        assert_eq!(picked, 20);
+        assert!(tried.len() <= MAX_STEPS);
+
+        // Noisy, not quite monotonic scores still end on a QP that met the target
+        let noisy = |qp: u32| 100.0 - 0.5 * qp as f64 + if qp.is_multiple_of(2) { 0.6 } else { -0.6 };
+        let (tried, picked) = run_search(30, 90.0, noisy);
+        assert!(noisy(picked) >= 90.0);
+        assert!(tried.iter().all(|qp| (MIN_QP..=MAX_QP).contains(qp)));
+    }
+}
@@ -2,10 +2,16 @@ use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, BorderType, Borders, Gauge, List, ListItem, Paragraph},
+    widgets::{Block, BorderType, Borders, Clear, Gauge, List, ListItem, ListState, Paragraph},
     Frame,
 };
-use crate::app::{App, Focus};
+use crate::app::{App, Focus, OverwritePrompt, TextPrompt};
+use crate::browser::FileBrowser;
+use crate::codecs;
+use crate::logs::{self, Level};
+use crate::logview::LogViewer;
+use crate::probe;
+use crate::queue::JobStatus;
 
 pub fn ui(f: &mut Frame, app: &App) {
     let chunks = Layout::default()
@@ -14,7 +20,7 @@ pub fn ui(f: &mut Frame, app: &App) {
         .constraints(
             [
                 Constraint::Length(3),  // Header
-                Constraint::Length(14), // Settings Grid
+                Constraint::Length(25), // Settings Grid
                 Constraint::Min(10),    // Dashboard (Stats + Log)
                 Constraint::Length(3),  // Footer
             ]
@@ -22,10 +28,234 @@ pub fn ui(f: &mut Frame, app: &App) {
         )
         .split(f.area());
 
+    let middle = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)].as_ref())
+        .split(chunks[1]);
+
     draw_header(f, chunks[0]);
-    draw_settings_grid(f, app, chunks[1]);
+    draw_settings_grid(f, app, middle[0]);
+    draw_queue(f, app, middle[1]);
     draw_dashboard(f, app, chunks[2]);
     draw_footer(f, chunks[3]);
+
+    if let Some(viewer) = &app.log_viewer {
+        draw_log_viewer(f, app, viewer, f.area());
+    }
+    if let Some(browser) = &app.browser {
+        draw_browser(f, browser, centered_rect(80, 80, f.area()));
+    }
+    if let Some(prompt) = &app.overwrite_prompt {
+        draw_overwrite_prompt(f, prompt, centered_rect(60, 30, f.area()));
+    }
+    if let Some(prompt) = &app.text_prompt {
+        draw_text_prompt(f, prompt, centered_rect(50, 20, f.area()));
+    }
+}
+
+fn draw_text_prompt(f: &mut Frame, prompt: &TextPrompt, area: Rect) {
+    f.render_widget(Clear, area);
+    let text = vec![
+        Line::from(""),
+        Line::from(vec![
+            Span::styled(format!(" {}", prompt.value), Style::default().fg(Color::White).add_modifier(Modifier::BOLD)),
+            Span::styled("█", Style::default().fg(Color::Magenta)),
+        ]),
+        Line::from(""),
+        Line::from(Span::styled(" [Enter] Save   [Esc] Cancel ", Style::default().fg(Color::DarkGray))),
+    ];
+    let dialog = Paragraph::new(text).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Magenta))
+            .border_type(BorderType::Thick)
+            .title(format!(" {} ", prompt.title.to_uppercase())),
+    );
+    f.render_widget(dialog, area);
+}
+
+fn draw_overwrite_prompt(f: &mut Frame, prompt: &OverwritePrompt, area: Rect) {
+    f.render_widget(Clear, area);
+    let text = vec![
+        Line::from(Span::styled("Output file already exists:", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))),
+        Line::from(""),
+        Line::from(Span::styled(prompt.output.display().to_string(), Style::default().fg(Color::White))),
+        Line::from(""),
+        Line::from(" [o] Overwrite   [s] Skip   [r] Rename "),
+        Line::from(" [O] Overwrite all   [S] Skip all   [Esc] Pause batch "),
+    ];
+    let dialog = Paragraph::new(text)
+        .alignment(ratatui::layout::Alignment::Center)
+        .wrap(ratatui::widgets::Wrap { trim: false })
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow))
+            .border_type(BorderType::Thick)
+            .title(" OVERWRITE? "));
+    f.render_widget(dialog, area);
+}
+
+// A rectangle of the given percentage size in the middle of `area`, for overlays
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ].as_ref())
+        .split(area);
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ].as_ref())
+        .split(vertical[1])[1]
+}
+
+fn draw_log_viewer(f: &mut Frame, app: &App, viewer: &LogViewer, area: Rect) {
+    f.render_widget(Clear, area);
+
+    let entry = viewer.source.and_then(|id| app.position_of(id));
+    let title = match entry {
+        Some(i) => format!(" LOG [{}] {} ({}) ", i + 1, app.queue[i].file_name(), app.queue[i].status.label()),
+        None if viewer.source.is_some() => " LOG: REMOVED ENTRY ".to_string(),
+        None => " LOG: SYSTEM ".to_string(),
+    };
+    let file = entry
+        .and_then(|i| app.queue[i].log_file.as_ref())
+        .map(|file| format!(" {} ", file.path.display()))
+        .unwrap_or_default();
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan))
+        .border_type(BorderType::Thick)
+        .title(title)
+        .title_bottom(Line::from(Span::styled(file, Style::default().fg(Color::Gray))));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(1)].as_ref())
+        .split(inner);
+
+    let lines = viewer.filtered(app.log_source_lines(viewer.source));
+    let height = chunks[0].height as usize;
+    let first = viewer.first_line(lines.len(), height);
+    let text: Vec<Line> = if lines.is_empty() {
+        vec![Line::from(Span::styled("Nothing logged at this level yet", Style::default().fg(Color::DarkGray)))]
+    } else {
+        lines
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(height)
+            .map(|(i, line)| log_line(viewer, line, viewer.current_match == Some(i)))
+            .collect()
+    };
+    f.render_widget(Paragraph::new(text), chunks[0]);
+
+    let hint = if viewer.typing {
+        Line::from(vec![
+            Span::styled(format!(" /{}", viewer.search), Style::default().fg(Color::White).add_modifier(Modifier::BOLD)),
+            Span::styled("█", Style::default().fg(Color::Magenta)),
+            Span::styled("  Enter: Find | Esc: Cancel", Style::default().fg(Color::DarkGray)),
+        ])
+    } else {
+        let shown = (first + 1).min(lines.len());
+        Line::from(Span::styled(
+            format!(
+                " {}-{} of {} | Level: {} | ↑↓ Scroll | PgUp/PgDn Page | Home/End | / Search | n/N Next/Prev | f Level | Tab/←→ Job | Esc Close ",
+                shown,
+                (first + height).min(lines.len()),
+                lines.len(),
+                viewer.min_level.label()
+            ),
+            Style::default().fg(Color::DarkGray),
+        ))
+    };
+    f.render_widget(Paragraph::new(hint), chunks[1]);
+}
+
+// A log line coloured by level, with the search matches highlighted
+fn log_line<'a>(viewer: &LogViewer, line: &'a str, current: bool) -> Line<'a> {
+    let style = match logs::level(line) {
+        Level::Error => Style::default().fg(Color::Red),
+        Level::Warning => Style::default().fg(Color::Yellow),
+        Level::Info => Style::default().fg(Color::Gray),
+        Level::Progress => Style::default().fg(Color::DarkGray),
+    };
+    let highlight = Style::default().fg(Color::Black).bg(if current { Color::Magenta } else { Color::Yellow });
+    let mut spans = Vec::new();
+    let mut end = 0;
+    for (start, stop) in viewer.match_ranges(line) {
+        spans.push(Span::styled(&line[end..start], style));
+        spans.push(Span::styled(&line[start..stop], highlight));
+        end = stop;
+    }
+    spans.push(Span::styled(&line[end..], style));
+    Line::from(spans)
+}
+
+fn draw_browser(f: &mut Frame, browser: &FileBrowser, area: Rect) {
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan))
+        .border_type(BorderType::Thick)
+        .title(format!(" ADD FILES: {} ", browser.cwd.display()));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(3), Constraint::Length(1)].as_ref())
+        .split(inner);
+
+    let items: Vec<ListItem> = if let Some(error) = &browser.error {
+        vec![ListItem::new(Line::from(Span::styled(error.clone(), Style::default().fg(Color::Red))))]
+    } else {
+        browser
+            .entries
+            .iter()
+            .map(|entry| {
+                if entry.is_dir {
+                    return ListItem::new(Line::from(Span::styled(
+                        format!("    📁 {}/", entry.name),
+                        Style::default().fg(Color::Blue).add_modifier(Modifier::BOLD),
+                    )));
+                }
+                let checked = if browser.selected.contains(&entry.path) { "[x]" } else { "[ ]" };
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!(" {} ", checked), Style::default().fg(Color::Magenta)),
+                    Span::styled(format!("{:<48}", entry.name), Style::default().fg(Color::White)),
+                    Span::styled(format!("{:>10}", probe::format_size(entry.size)), Style::default().fg(Color::Gray)),
+                    Span::styled(
+                        format!("{:>10}", entry.duration.as_deref().unwrap_or("…")),
+                        Style::default().fg(Color::Yellow),
+                    ),
+                ]))
+            })
+            .collect()
+    };
+
+    let list = List::new(items)
+        .highlight_style(Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD));
+    let mut state = ListState::default().with_selected(Some(browser.cursor));
+    f.render_stateful_widget(list, chunks[0], &mut state);
+
+    let filter = if browser.show_all_files { "all files" } else { "video files" };
+    let hint = Paragraph::new(format!(
+        " {} selected | Showing {} | Enter: Open/Add | Space: Select | a: All | .: Toggle Filter | ←/Backspace: Up | Esc: Close ",
+        browser.selected.len(),
+        filter
+    ))
+        .style(Style::default().fg(Color::DarkGray));
+    f.render_widget(hint, chunks[1]);
 }
 
 fn draw_header(f: &mut Frame, area: Rect) {
@@ -44,51 +274,120 @@ fn draw_header(f: &mut Frame, area: Rect) {
 fn draw_settings_grid(f: &mut Frame, app: &App, area: Rect) {
     let settings_chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Length(3), Constraint::Length(3), Constraint::Length(3), Constraint::Length(3)].as_ref())
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(1),
+            Constraint::Length(3),
+        ].as_ref())
         .split(area);
 
-    let row1 = Layout::default()
+    let row0 = Layout::default()
         .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)].as_ref())
         .split(settings_chunks[0]);
+
+    let row1 = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(20), Constraint::Percentage(30)].as_ref())
+        .split(settings_chunks[1]);
     
     let row2 = Layout::default()
         .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
-        .split(settings_chunks[1]);
+        .constraints([Constraint::Percentage(35), Constraint::Percentage(30), Constraint::Percentage(35)].as_ref())
+        .split(settings_chunks[2]);
 
     let row3 = Layout::default()
         .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
-        .split(settings_chunks[2]);
+        .constraints([Constraint::Percentage(30), Constraint::Percentage(40), Constraint::Percentage(30)].as_ref())
+        .split(settings_chunks[3]);
+
+    // Preset
+    let preset_style = if let Focus::Preset = app.focus { Style::default().fg(Color::Magenta) } else { Style::default().fg(Color::DarkGray) };
+    let preset_label = match app.selected_preset() {
+        Some(preset) => preset.name.clone(),
+        None => "Custom".to_string(),
+    };
+    let preset_widget = Paragraph::new(format!(" < {} > ", preset_label))
+        .block(Block::default().borders(Borders::ALL).border_style(preset_style).title(" PRESET ").border_type(BorderType::Rounded))
+        .style(Style::default().fg(Color::White).add_modifier(Modifier::BOLD));
+    f.render_widget(preset_widget, row0[0]);
+
+    // Preset actions (Read Only)
+    let preset_hint = match app.selected_preset_index {
+        Some(i) if i < app.builtin_preset_count => "built-in · n copy · E export",
+        Some(_) => "n new · e rename · x delete · E export",
+        None => "n save as preset",
+    };
+    let preset_hint_widget = Paragraph::new(preset_hint)
+        .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(Color::Blue)).title(" PRESETS ").border_type(BorderType::Rounded))
+        .style(Style::default().fg(Color::Gray));
+    f.render_widget(preset_hint_widget, row0[1]);
 
     // 1. Encoder
     let encoder_style = if let Focus::Encoder = app.focus { Style::default().fg(Color::Magenta) } else { Style::default().fg(Color::DarkGray) };
-    let encoder_widget = Paragraph::new(format!(" < {} > ", app.encoders[app.selected_encoder_index]))
-        .block(Block::default().borders(Borders::ALL).border_style(encoder_style).title(" CODEC ").border_type(BorderType::Rounded))
-        .style(Style::default().fg(Color::White).add_modifier(Modifier::BOLD));
+    // Encoders that failed the startup check are listed greyed out after the selection
+    let encoder = &app.encoders[app.selected_encoder_index];
+    let encoder_color = if app.encoder_problem(encoder).is_some() { Color::DarkGray } else { Color::White };
+    let mut encoder_spans = vec![Span::styled(format!(" < {} > ", encoder), Style::default().fg(encoder_color).add_modifier(Modifier::BOLD))];
+    if app.encoder_detection.is_some() {
+        encoder_spans.push(Span::styled("checking…", Style::default().fg(Color::DarkGray)));
+    }
+    for (name, _) in app.unavailable_encoders.iter().filter(|(name, _)| name != encoder) {
+        encoder_spans.push(Span::styled(format!("✖{} ", name), Style::default().fg(Color::DarkGray).add_modifier(Modifier::CROSSED_OUT)));
+    }
+    let encoder_widget = Paragraph::new(Line::from(encoder_spans))
+        .block(Block::default().borders(Borders::ALL).border_style(encoder_style).title(" CODEC ").border_type(BorderType::Rounded));
     f.render_widget(encoder_widget, row1[0]);
 
+    // Hardware decoding
+    let decode_style = if let Focus::HwDecode = app.focus { Style::default().fg(Color::Magenta) } else { Style::default().fg(Color::DarkGray) };
+    let decode_widget = Paragraph::new(format!(" < {} > ", app.hw_decode_list[app.selected_hw_decode_index]))
+        .block(Block::default().borders(Borders::ALL).border_style(decode_style).title(" DECODE ").border_type(BorderType::Rounded))
+        .style(Style::default().fg(Color::White).add_modifier(Modifier::BOLD));
+    f.render_widget(decode_widget, row1[1]);
+
     // 2. Container
     let container_style = if let Focus::Container = app.focus { Style::default().fg(Color::Magenta) } else { Style::default().fg(Color::DarkGray) };
     let container_widget = Paragraph::new(format!(" < {} > ", app.container_list[app.selected_container_index]))
         .block(Block::default().borders(Borders::ALL).border_style(container_style).title(" FORMAT ").border_type(BorderType::Rounded))
         .style(Style::default().fg(Color::White).add_modifier(Modifier::BOLD));
-    f.render_widget(container_widget, row1[1]);
+    f.render_widget(container_widget, row1[2]);
 
     // 3. QP
     let qp_style = if let Focus::Qp = app.focus { Style::default().fg(Color::Magenta) } else { Style::default().fg(Color::DarkGray) };
+    // With a quality target the QP is only the search's starting point
+    // ProRes, DNxHR and FFV1 pick their quality from the profile instead
+    let qp_title = if !codecs::uses_qp(encoder) {
+        " QP (unused) "
+    } else if app.target_vmaf().is_some() {
+        " START QP "
+    } else {
+        " QUALITY (QP) "
+    };
     let qp_widget = Paragraph::new(format!(" < {} > ", app.qp_list[app.selected_qp_index]))
-        .block(Block::default().borders(Borders::ALL).border_style(qp_style).title(" QUALITY (QP) ").border_type(BorderType::Rounded))
+        .block(Block::default().borders(Borders::ALL).border_style(qp_style).title(qp_title).border_type(BorderType::Rounded))
         .style(Style::default().fg(Color::White).add_modifier(Modifier::BOLD));
     f.render_widget(qp_widget, row2[0]);
 
+    // Target Quality
+    let target_style = if let Focus::TargetVmaf = app.focus { Style::default().fg(Color::Magenta) } else { Style::default().fg(Color::DarkGray) };
+    let target_widget = Paragraph::new(format!(" < {} > ", app.target_vmaf_list[app.selected_target_vmaf_index]))
+        .block(Block::default().borders(Borders::ALL).border_style(target_style).title(" TARGET VMAF ").border_type(BorderType::Rounded))
+        .style(Style::default().fg(Color::White).add_modifier(Modifier::BOLD));
+    f.render_widget(target_widget, row2[1]);
+
     // 4. FPS
     let fps_style = if let Focus::Fps = app.focus { Style::default().fg(Color::Magenta) } else { Style::default().fg(Color::DarkGray) };
     let fps_widget = Paragraph::new(format!(" < {} > ", app.fps_list[app.selected_fps_index]))
         .block(Block::default().borders(Borders::ALL).border_style(fps_style).title(" FPS ").border_type(BorderType::Rounded))
         .style(Style::default().fg(Color::White).add_modifier(Modifier::BOLD));
-    f.render_widget(fps_widget, row2[1]);
+    f.render_widget(fps_widget, row2[2]);
 
     // 5. Audio
     let audio_style = if let Focus::AudioBitrate = app.focus { Style::default().fg(Color::Magenta) } else { Style::default().fg(Color::DarkGray) };
@@ -97,28 +396,258 @@ fn draw_settings_grid(f: &mut Frame, app: &App, area: Rect) {
         .style(Style::default().fg(Color::White).add_modifier(Modifier::BOLD));
     f.render_widget(audio_widget, row3[0]);
 
-    // 6. File Info (Read Only)
-    let (current_input, current_output) = if let Some((input, output)) = app.get_current_file() {
-        (input, output)
-    } else if app.queue.is_empty() {
-        ("No file selected".to_string(), "".to_string())
+    // 6. Pixel Format
+    let pix_fmt_error = app.pix_fmt_error();
+    let pix_fmt_style = if let Focus::PixFmt = app.focus { Style::default().fg(Color::Magenta) } else { Style::default().fg(Color::DarkGray) };
+    let pix_fmt_label = if pix_fmt_error.is_some() {
+        format!(" < {} > (unsupported) ", app.pix_fmt_list[app.selected_pix_fmt_index])
     } else {
-        ("All files processed".to_string(), "".to_string())
+        format!(" < {} > ", app.pix_fmt_list[app.selected_pix_fmt_index])
     };
-    
-    let file_info = Paragraph::new(format!("IN: {}\nOUT: {}\nQueue: {}/{}", current_input, current_output, app.current_file_index + 1, app.queue.len()))
-        .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(Color::Blue)).title(" FILES ").border_type(BorderType::Rounded))
+    let pix_fmt_widget = Paragraph::new(pix_fmt_label)
+        .block(Block::default().borders(Borders::ALL).border_style(pix_fmt_style).title(" PIXEL FORMAT ").border_type(BorderType::Rounded))
+        .style(Style::default().fg(if pix_fmt_error.is_some() { Color::Red } else { Color::White }).add_modifier(Modifier::BOLD));
+    f.render_widget(pix_fmt_widget, row3[1]);
+
+    // 7. Profile
+    let profile_style = if let Focus::Profile = app.focus { Style::default().fg(Color::Magenta) } else { Style::default().fg(Color::DarkGray) };
+    let profile_widget = Paragraph::new(format!(" < {} > ", app.profile_list[app.selected_profile_index]))
+        .block(Block::default().borders(Borders::ALL).border_style(profile_style).title(" PROFILE ").border_type(BorderType::Rounded))
+        .style(Style::default().fg(Color::White).add_modifier(Modifier::BOLD));
+    f.render_widget(profile_widget, row3[2]);
+
+    let args_row = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(40), Constraint::Percentage(60)].as_ref())
+        .split(settings_chunks[4]);
+
+    // Extra ffmpeg arguments (Enter to edit)
+    for (focus, value, title, area) in [
+        (matches!(app.focus, Focus::InputArgs), &app.input_args, " INPUT ARGS ", args_row[0]),
+        (matches!(app.focus, Focus::OutputArgs), &app.output_args, " OUTPUT ARGS ", args_row[1]),
+    ] {
+        let style = if focus { Style::default().fg(Color::Magenta) } else { Style::default().fg(Color::DarkGray) };
+        let (text, color) = if value.trim().is_empty() { (" (none) ".to_string(), Color::DarkGray) } else { (format!(" {} ", value), Color::White) };
+        let widget = Paragraph::new(text)
+            .block(Block::default().borders(Borders::ALL).border_style(style).title(title).border_type(BorderType::Rounded))
+            .style(Style::default().fg(color).add_modifier(Modifier::BOLD));
+        f.render_widget(widget, area);
+    }
+
+    let row4 = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage(13),
+            Constraint::Percentage(13),
+            Constraint::Percentage(16),
+            Constraint::Percentage(13),
+            Constraint::Percentage(13),
+            Constraint::Percentage(32),
+        ].as_ref())
+        .split(settings_chunks[5]);
+
+    // 7. Parallel Jobs
+    let jobs_style = if let Focus::MaxJobs = app.focus { Style::default().fg(Color::Magenta) } else { Style::default().fg(Color::DarkGray) };
+    let jobs_widget = Paragraph::new(format!(" < {} > ", app.max_jobs_list[app.selected_max_jobs_index]))
+        .block(Block::default().borders(Borders::ALL).border_style(jobs_style).title(" PARALLEL JOBS ").border_type(BorderType::Rounded))
+        .style(Style::default().fg(Color::White).add_modifier(Modifier::BOLD));
+    f.render_widget(jobs_widget, row4[0]);
+
+    // 8. Threads per Job
+    let threads_style = if let Focus::Threads = app.focus { Style::default().fg(Color::Magenta) } else { Style::default().fg(Color::DarkGray) };
+    let threads_widget = Paragraph::new(format!(" < {} > ", app.threads_list[app.selected_threads_index]))
+        .block(Block::default().borders(Borders::ALL).border_style(threads_style).title(" THREADS/JOB ").border_type(BorderType::Rounded))
+        .style(Style::default().fg(Color::White).add_modifier(Modifier::BOLD));
+    f.render_widget(threads_widget, row4[1]);
+
+    // VAAPI/QSV device, shown by its node name
+    let device_style = if let Focus::HwDevice = app.focus { Style::default().fg(Color::Magenta) } else { Style::default().fg(Color::DarkGray) };
+    let device = &app.hw_device_list[app.selected_hw_device_index];
+    let device_label = device.rsplit('/').next().unwrap_or(device);
+    let device_widget = Paragraph::new(format!(" < {} > ", device_label))
+        .block(Block::default().borders(Borders::ALL).border_style(device_style).title(" HW DEVICE ").border_type(BorderType::Rounded))
+        .style(Style::default().fg(Color::White).add_modifier(Modifier::BOLD));
+    f.render_widget(device_widget, row4[2]);
+
+    // 9. Chunked Mode
+    let chunk_style = if let Focus::ChunkMode = app.focus { Style::default().fg(Color::Magenta) } else { Style::default().fg(Color::DarkGray) };
+    let chunk_widget = Paragraph::new(format!(" < {} > ", app.chunk_mode_list[app.selected_chunk_mode_index]))
+        .block(Block::default().borders(Borders::ALL).border_style(chunk_style).title(" CHUNKED ").border_type(BorderType::Rounded))
+        .style(Style::default().fg(Color::White).add_modifier(Modifier::BOLD));
+    f.render_widget(chunk_widget, row4[3]);
+
+    // 10. Post-encode Verification
+    let verify_style = if let Focus::Verify = app.focus { Style::default().fg(Color::Magenta) } else { Style::default().fg(Color::DarkGray) };
+    let verify_widget = Paragraph::new(format!(" < {} > ", app.verify_mode()))
+        .block(Block::default().borders(Borders::ALL).border_style(verify_style).title(" VERIFY ").border_type(BorderType::Rounded))
+        .style(Style::default().fg(Color::White).add_modifier(Modifier::BOLD));
+    f.render_widget(verify_widget, row4[4]);
+
+    // 11. What the grid is editing (Read Only)
+    let editing = match app.selected_queue_index {
+        Some(i) if app.is_editable(i) => format!("[{}] {}", i + 1, app.queue[i].file_name()),
+        Some(i) => format!("[{}] {} (locked)", i + 1, app.queue[i].file_name()),
+        None => "Defaults for new files".to_string(),
+    };
+    let editing_info = Paragraph::new(editing)
+        .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(Color::Blue)).title(" EDITING [ ] ").border_type(BorderType::Rounded))
         .style(Style::default().fg(Color::Gray));
-    f.render_widget(file_info, row3[1]);
+    f.render_widget(editing_info, row4[5]);
+
+    let row5 = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage(27),
+            Constraint::Percentage(12),
+            Constraint::Percentage(11),
+            Constraint::Percentage(11),
+            Constraint::Percentage(12),
+            Constraint::Percentage(27),
+        ].as_ref())
+        .split(settings_chunks[6]);
 
-    // 7. Submit Button
+    // 12. Output Naming Template
+    let template_style = if let Focus::OutputTemplate = app.focus { Style::default().fg(Color::Magenta) } else { Style::default().fg(Color::DarkGray) };
+    let template_widget = Paragraph::new(format!(" < {} > ", app.output_template()))
+        .block(Block::default().borders(Borders::ALL).border_style(template_style).title(" OUTPUT NAME ").border_type(BorderType::Rounded))
+        .style(Style::default().fg(Color::White).add_modifier(Modifier::BOLD));
+    f.render_widget(template_widget, row5[0]);
+
+    // 13. Existing Output Policy
+    let overwrite_style = if let Focus::Overwrite = app.focus { Style::default().fg(Color::Magenta) } else { Style::default().fg(Color::DarkGray) };
+    let overwrite_widget = Paragraph::new(format!(" < {} > ", app.overwrite_policy()))
+        .block(Block::default().borders(Borders::ALL).border_style(overwrite_style).title(" IF EXISTS ").border_type(BorderType::Rounded))
+        .style(Style::default().fg(Color::White).add_modifier(Modifier::BOLD));
+    f.render_widget(overwrite_widget, row5[1]);
+
+    // 14. Quality Metrics
+    let metrics_style = if let Focus::Metrics = app.focus { Style::default().fg(Color::Magenta) } else { Style::default().fg(Color::DarkGray) };
+    let metrics_widget = Paragraph::new(format!(" < {} > ", app.metrics_mode()))
+        .block(Block::default().borders(Borders::ALL).border_style(metrics_style).title(" METRICS ").border_type(BorderType::Rounded))
+        .style(Style::default().fg(Color::White).add_modifier(Modifier::BOLD));
+    f.render_widget(metrics_widget, row5[2]);
+
+    // Automatic retries after a failure
+    let retries_style = if let Focus::Retries = app.focus { Style::default().fg(Color::Magenta) } else { Style::default().fg(Color::DarkGray) };
+    let retries_widget = Paragraph::new(format!(" < {} > ", app.retries_list[app.selected_retries_index]))
+        .block(Block::default().borders(Borders::ALL).border_style(retries_style).title(" RETRIES ").border_type(BorderType::Rounded))
+        .style(Style::default().fg(Color::White).add_modifier(Modifier::BOLD));
+    f.render_widget(retries_widget, row5[3]);
+
+    // Fallback chain once the retries are used up
+    let fallback_style = if let Focus::Fallback = app.focus { Style::default().fg(Color::Magenta) } else { Style::default().fg(Color::DarkGray) };
+    let fallback_widget = Paragraph::new(format!(" < {} > ", app.fallback_list[app.selected_fallback_index]))
+        .block(Block::default().borders(Borders::ALL).border_style(fallback_style).title(" FALLBACK ").border_type(BorderType::Rounded))
+        .style(Style::default().fg(Color::White).add_modifier(Modifier::BOLD));
+    f.render_widget(fallback_widget, row5[4]);
+
+    // 15. Output path of the entry being edited (Read Only)
+    let output_preview = match app.selected_queue_index {
+        Some(i) => match app.output_path(i) {
+            Ok(path) => path.to_string_lossy().to_string(),
+            Err(e) => e,
+        },
+        None => match &app.output_dir {
+            Some(dir) => format!("{{dir}} = {}", dir.display()),
+            None => "{dir} = input file's folder".to_string(),
+        },
+    };
+    let output_widget = Paragraph::new(output_preview)
+        .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(Color::Blue)).title(" OUTPUT ").border_type(BorderType::Rounded))
+        .style(Style::default().fg(Color::Gray));
+    f.render_widget(output_widget, row5[5]);
+
+    // 16. Compatibility warning
+    let encoder_error = app.encoder_problem(encoder).map(|reason| format!("{} is unavailable: {}", encoder, reason));
+    let warning = match (pix_fmt_error.or(app.container_error()).or(encoder_error), app.playback_warning().or(app.decode_warning())) {
+        (Some(err), _) => Line::from(Span::styled(format!(" ✖ {}", err), Style::default().fg(Color::Red))),
+        (None, Some(warning)) => Line::from(Span::styled(format!(" ⚠ {}", warning), Style::default().fg(Color::Yellow))),
+        (None, None) if matches!(app.focus, Focus::Encoder) && !app.unavailable_encoders.is_empty() => {
+            let reasons: Vec<String> = app.unavailable_encoders.iter().map(|(name, reason)| format!("{}: {}", name, reason)).collect();
+            Line::from(Span::styled(format!(" Unavailable: {}", reasons.join("; ")), Style::default().fg(Color::DarkGray)))
+        }
+        (None, None) => Line::from(""),
+    };
+    f.render_widget(Paragraph::new(warning), settings_chunks[7]);
+
+    // 17. Submit Button
     let submit_style = if let Focus::Submit = app.focus { Style::default().fg(Color::Green) } else { Style::default().fg(Color::DarkGray) };
     let submit_text = if app.is_encoding { " [ ENCODING IN PROGRESS... ] " } else { " [ START ENCODING ] " };
     let submit_widget = Paragraph::new(submit_text)
         .alignment(ratatui::layout::Alignment::Center)
         .block(Block::default().borders(Borders::ALL).border_style(submit_style).border_type(BorderType::Thick))
         .style(Style::default().fg(if app.is_encoding { Color::Yellow } else { Color::Green }).add_modifier(Modifier::BOLD));
-    f.render_widget(submit_widget, settings_chunks[3]);
+    f.render_widget(submit_widget, settings_chunks[8]);
+}
+
+fn draw_queue(f: &mut Frame, app: &App, area: Rect) {
+    let collisions = app.output_collisions();
+    let items: Vec<ListItem> = app
+        .queue
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let (marker, color) = match item.status {
+                JobStatus::Pending => ("·", Color::Gray),
+                JobStatus::Searching => ("⌕", Color::Cyan),
+                JobStatus::Encoding => ("⟳", Color::Yellow),
+                JobStatus::Verifying => ("◎", Color::Cyan),
+                JobStatus::Measuring => ("◈", Color::Cyan),
+                JobStatus::Done => ("✔", Color::Green),
+                JobStatus::Failed(_) | JobStatus::VerifyFailed(_) => ("✖", Color::Red),
+                JobStatus::Skipped => ("»", Color::DarkGray),
+                JobStatus::Cancelled => ("⊘", Color::DarkGray),
+            };
+            let selected = app.selected_queue_index == Some(i);
+            let name_style = if selected {
+                Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            let mut lines = vec![
+                Line::from(vec![
+                    Span::styled(format!(" {} ", marker), Style::default().fg(color)),
+                    Span::styled(format!("[{}] {}", i + 1, item.file_name()), name_style),
+                    Span::styled(format!(" {}", item.status.label()), Style::default().fg(color)),
+                ]),
+                Line::from(Span::styled(format!("     {}", item.settings.summary()), Style::default().fg(Color::DarkGray))),
+            ];
+            // The last attempt of a failed entry is the failure shown below
+            let failed = matches!(item.status, JobStatus::Failed(_) | JobStatus::VerifyFailed(_));
+            let earlier = if failed { item.attempts.len().saturating_sub(1) } else { item.attempts.len() };
+            for (n, attempt) in item.attempts[..earlier].iter().enumerate() {
+                lines.push(Line::from(Span::styled(
+                    format!("     attempt {}: {} ✖ {}", n + 1, attempt.summary, attempt.error),
+                    Style::default().fg(Color::DarkGray),
+                )));
+            }
+            if let Some(scores) = &item.metrics {
+                lines.push(Line::from(Span::styled(format!("     {}", scores.summary()), Style::default().fg(Color::Green))));
+            }
+            if let JobStatus::Failed(reason) | JobStatus::VerifyFailed(reason) = &item.status {
+                lines.push(Line::from(Span::styled(format!("     {}", reason), Style::default().fg(Color::Red))));
+            }
+            if let Some(others) = collisions.get(&i) {
+                let others: Vec<String> = others.iter().map(|o| format!("[{}]", o + 1)).collect();
+                lines.push(Line::from(Span::styled(
+                    format!("     ⚠ same output as {}", others.join(", ")),
+                    Style::default().fg(Color::Yellow),
+                )));
+            }
+            ListItem::new(lines)
+        })
+        .collect();
+
+    let queue_list = List::new(items)
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Blue))
+            .title(format!(" QUEUE ({}) ", app.queue.len()))
+            .border_type(BorderType::Rounded));
+
+    // Stateful render only to keep the selected entry scrolled into view
+    let mut state = ListState::default().with_selected(app.selected_queue_index);
+    f.render_stateful_widget(queue_list, area, &mut state);
 }
 
 fn draw_dashboard(f: &mut Frame, app: &App, area: Rect) {
@@ -131,15 +660,22 @@ fn draw_dashboard(f: &mut Frame, app: &App, area: Rect) {
     let dashboard_chunks = Layout::default()
         .direction(Direction::Vertical)
         .margin(1)
-        .constraints([Constraint::Length(3), Constraint::Length(3), Constraint::Min(5)].as_ref())
+        .constraints([
+            Constraint::Length(2),
+            Constraint::Length(3),
+            Constraint::Length(app.active_jobs.len() as u16),
+            Constraint::Length(if app.shown_diagnosis().is_some() { 4 } else { 0 }),
+            Constraint::Min(5),
+        ].as_ref())
         .split(area);
 
-    // Progress Bar
+    // Aggregate Progress Bar
+    let overall = app.overall_progress();
     let gauge = Gauge::default()
         .block(Block::default().borders(Borders::NONE))
         .gauge_style(Style::default().fg(Color::Cyan).bg(Color::DarkGray))
-        .ratio(app.progress)
-        .label(format!("{:.1}%", app.progress * 100.0));
+        .ratio(overall)
+        .label(format!("TOTAL {:.1}%", overall * 100.0));
     f.render_widget(gauge, dashboard_chunks[0]);
 
     // Stats Grid
@@ -148,11 +684,16 @@ fn draw_dashboard(f: &mut Frame, app: &App, area: Rect) {
         .constraints([Constraint::Percentage(25), Constraint::Percentage(25), Constraint::Percentage(25), Constraint::Percentage(25)].as_ref())
         .split(dashboard_chunks[1]);
 
+    let total_fps: f64 = app.active_jobs.iter().filter_map(|j| j.fps.parse::<f64>().ok()).sum();
     let stats = [
-        ("FPS", &app.fps),
-        ("SPEED", &app.speed),
-        ("BITRATE", &app.bitrate),
-        ("TIME", &app.time),
+        ("ACTIVE JOBS", format!("{}/{}", app.active_jobs.len(), app.concurrent_files())),
+        ("TOTAL FPS", format!("{:.0}", total_fps)),
+        ("DONE", app.count_status(&JobStatus::Done).to_string()),
+        (
+            "FAILED",
+            (app.count_status(&JobStatus::Failed(String::new())) + app.count_status(&JobStatus::VerifyFailed(String::new())))
+                .to_string(),
+        ),
     ];
 
     for (i, (label, value)) in stats.iter().enumerate() {
@@ -163,23 +704,98 @@ fn draw_dashboard(f: &mut Frame, app: &App, area: Rect) {
         f.render_widget(p, stats_layout[i]);
     }
 
+    // One progress row per running job
+    let job_rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(app.active_jobs.iter().map(|_| Constraint::Length(1)).collect::<Vec<_>>())
+        .split(dashboard_chunks[2]);
+
+    for (job, row) in app.active_jobs.iter().zip(job_rows.iter()) {
+        let Some(index) = app.position_of(job.id) else { continue };
+        let job_gauge = Gauge::default()
+            .gauge_style(Style::default().fg(Color::Magenta).bg(Color::Black))
+            .ratio(job.progress)
+            .label(format!(
+                "[{}] {} {:.1}% | {} fps | {} | {} | {}",
+                index + 1,
+                app.queue[index].file_name(),
+                job.progress * 100.0,
+                job.fps,
+                job.speed,
+                job.bitrate,
+                job.time
+            ));
+        f.render_widget(job_gauge, *row);
+    }
+
+    // Why the latest (or selected) entry failed
+    if let Some((index, diagnosis)) = app.shown_diagnosis() {
+        let lines = vec![
+            Line::from(Span::styled(format!("✖ {}", diagnosis.problem), Style::default().fg(Color::Red).add_modifier(Modifier::BOLD))),
+            Line::from(Span::styled(format!("→ {}", diagnosis.fix), Style::default().fg(Color::Yellow))),
+        ];
+        let panel = Paragraph::new(lines)
+            .wrap(ratatui::widgets::Wrap { trim: true })
+            .block(Block::default()
+                .borders(Borders::TOP)
+                .title(format!(" DIAGNOSIS [{}] {} ", index + 1, app.queue[index].file_name()))
+                .border_style(Style::default().fg(Color::Red)));
+        f.render_widget(panel, dashboard_chunks[3]);
+    }
+
+    if app.show_commands {
+        draw_commands(f, app, dashboard_chunks[4]);
+        return;
+    }
+
     // Logs
     let logs: Vec<ListItem> = app
         .log_messages
         .iter()
         .rev()
-        .take(10)
+        .take(app.log_lines)
         .map(|m| ListItem::new(Line::from(Span::raw(m))))
         .collect();
     
     let log_list = List::new(logs)
         .block(Block::default().borders(Borders::TOP).title(" SYSTEM LOGS ").border_style(Style::default().fg(Color::DarkGray)))
         .style(Style::default().fg(Color::Gray));
-    f.render_widget(log_list, dashboard_chunks[2]);
+    f.render_widget(log_list, dashboard_chunks[4]);
+}
+
+// Command preview for the selected entry, in place of the log
+fn draw_commands(f: &mut Frame, app: &App, area: Rect) {
+    let mut lines = Vec::new();
+    match app.selected_queue_index {
+        None => lines.push(Line::from(Span::styled("Pick a queue entry with [ ] to see its commands", Style::default().fg(Color::DarkGray)))),
+        Some(_) => match &app.command_preview {
+            None => {}
+            Some(Ok(commands)) => {
+                for command in commands {
+                    lines.push(Line::from(Span::styled(format!("# {}", command.label), Style::default().fg(Color::DarkGray))));
+                    if !command.line.is_empty() {
+                        lines.push(Line::from(Span::styled(command.line.as_str(), Style::default().fg(Color::White))));
+                    }
+                }
+            }
+            Some(Err(e)) => lines.push(Line::from(Span::styled(format!("✖ {}", e), Style::default().fg(Color::Red)))),
+        },
+    }
+
+    // The log is hidden meanwhile, so keep its latest line visible for copy/export results
+    let last_log = app.log_messages.last().map(|m| format!(" {} ", m)).unwrap_or_default();
+    let panel = Paragraph::new(lines)
+        .wrap(ratatui::widgets::Wrap { trim: false })
+        .block(Block::default()
+            .borders(Borders::TOP | Borders::BOTTOM)
+            .title(" COMMANDS (p hide | y copy | Y save script) ")
+            .title_bottom(Line::from(Span::styled(last_log, Style::default().fg(Color::Gray))))
+            .border_style(Style::default().fg(Color::DarkGray)));
+    f.render_widget(panel, area);
 }
 
 fn draw_footer(f: &mut Frame, area: Rect) {
-    let footer = Paragraph::new(" Controls: Arrows to Navigate | Enter to Select | Drag & Drop File to Open ")
+    let footer = Paragraph::new(" Arrows: Navigate | Enter: Select | o/Drop/Paste: Add Files | [ ]: Pick Entry | Del Remove | PgUp/PgDn Move | d Dup | s Skip | c Cancel | r/R Retry | C Clear Done | w Save Defaults | p Commands | l Log ")
         .style(Style::default().fg(Color::DarkGray))
         .alignment(ratatui::layout::Alignment::Center);
     f.render_widget(footer, area);
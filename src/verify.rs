@@ -0,0 +1,190 @@
+// Checks run on a finished encode before it is moved into place: the output is
+// probed and compared with the input, and optionally decoded in full to catch
+// corruption that probing alone misses.
+use std::path::Path;
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::thread;
+use crate::ffmpeg::{self, FfmpegEvent};
+
+pub const VERIFY_OFF: &str = "Off";
+pub const VERIFY_PROBE: &str = "Probe";
+pub const VERIFY_DECODE: &str = "Decode"; // probe, then decode every frame
+
+// Durations may differ by this much, or by this share of the input, whichever is larger
+const DURATION_TOLERANCE_SECS: f64 = 0.5;
+const DURATION_TOLERANCE_RATIO: f64 = 0.01;
+
+pub fn verify_mode_choices() -> Vec<String> {
+    vec![VERIFY_OFF.to_string(), VERIFY_PROBE.to_string(), VERIFY_DECODE.to_string()]
+}
+
+pub struct MediaInfo {
+    pub duration: Option<f64>,
+    pub video_streams: usize,
+    pub audio_streams: usize,
+}
+
+pub fn probe_command(path: &Path) -> Command {
+    let mut cmd = ffmpeg::ffprobe_command();
+    cmd.args(["-v", "error", "-show_entries", "format=duration:stream=codec_type", "-of", "default=nw=1"])
+        .arg(path);
+    cmd
+}
+
+pub fn probe_media(path: &Path) -> Result<MediaInfo, String> {
+    let output = probe_command(path)
+        .output()
+        .map_err(|e| format!("Failed to run ffprobe: {}", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("ffprobe cannot read {}: {}", path.display(), stderr.lines().next().unwrap_or("unknown error")));
+    }
+
+    Ok(parse_media_info(&String::from_utf8_lossy(&output.stdout)))
+}
+
+// `probe_command`'s key=value lines: one codec_type per stream, then the duration
+pub fn parse_media_info(text: &str) -> MediaInfo {
+    let mut info = MediaInfo { duration: None, video_streams: 0, audio_streams: 0 };
+    for line in text.lines() {
+        match line.trim().split_once('=') {
+            Some(("duration", value)) => info.duration = value.parse().ok(),
+            Some(("codec_type", "video")) => info.video_streams += 1,
+            Some(("codec_type", "audio")) => info.audio_streams += 1,
+            _ => {}
+        }
+    }
+    info
+}
+
+// Every mismatch between input and output, joined into one message
+pub fn compare(input: &MediaInfo, output: &MediaInfo, container: &str) -> Result<(), String> {
+    let mut problems = Vec::new();
+
+    match (input.duration, output.duration) {
+        (Some(expected), Some(actual)) => {
+            let tolerance = DURATION_TOLERANCE_SECS.max(expected * DURATION_TOLERANCE_RATIO);
+            if (expected - actual).abs() > tolerance {
+                problems.push(format!("duration {:.2}s, input is {:.2}s", actual, expected));
+            }
+        }
+        (Some(_), None) => problems.push("output has no duration".to_string()),
+        _ => {} // Nothing to compare against
+    }
+
+    // ffmpeg keeps one video and one audio stream; gif has no audio
+    let expected_video = input.video_streams.min(1);
+    let expected_audio = if container == "gif" { 0 } else { input.audio_streams.min(1) };
+    if output.video_streams != expected_video {
+        problems.push(format!("{} video stream(s), expected {}", output.video_streams, expected_video));
+    }
+    if output.audio_streams != expected_audio {
+        problems.push(format!("{} audio stream(s), expected {}", output.audio_streams, expected_audio));
+    }
+
+    if problems.is_empty() { Ok(()) } else { Err(problems.join("; ")) }
+}
+
+// Decodes the whole file to the null muxer; anything ffmpeg reports at error level counts
+pub fn decode_command(path: &Path) -> Command {
+    let mut cmd = ffmpeg::ffmpeg_command();
+    cmd.args(["-nostdin", "-v", "error", "-i"]).arg(path).args(["-f", "null", "-"]);
+    cmd
+}
+
+fn decode_check(path: &Path, cancel: &AtomicBool) -> Result<(), String> {
+    let (status, stderr) = ffmpeg::run_cancellable(decode_command(path), cancel)?;
+
+    let errors: Vec<&str> = stderr.lines().collect();
+    if !status.success() || !errors.is_empty() {
+        let first = errors.first().map(|e| e.to_string()).unwrap_or_else(|| format!("ffmpeg exited with {}", status));
+        return Err(format!("decode failed with {} error line(s), first: {}", errors.len(), first));
+    }
+    Ok(())
+}
+
+pub fn start_verification(
+    job: usize,
+    input: String,
+    output: String,
+    container: String,
+    mode: String,
+    cancel: Arc<AtomicBool>,
+    tx: Sender<FfmpegEvent>,
+) {
+    thread::spawn(move || {
+        let _ = tx.send(FfmpegEvent::Log(job, format!("Verifying output ({})...", mode.to_lowercase())));
+
+        let result = probe_media(Path::new(&input))
+            .and_then(|input_info| probe_media(Path::new(&output)).map(|output_info| (input_info, output_info)))
+            .and_then(|(input_info, output_info)| compare(&input_info, &output_info, &container))
+            .and_then(|_| if mode == VERIFY_DECODE { decode_check(Path::new(&output), &cancel) } else { Ok(()) });
+
+        let event = match result {
+            Ok(()) => FfmpegEvent::Verified(job),
+            Err(e) if cancel.load(Ordering::SeqCst) => FfmpegEvent::Error(job, e),
+            Err(e) => FfmpegEvent::VerifyFailed(job, e),
+        };
+        let _ = tx.send(event);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ffprobe output for a movie with one video, two audio and a subtitle stream
+    const INPUT: &str = "\
+codec_type=video
+codec_type=audio
+codec_type=audio
+codec_type=subtitle
+duration=600.040000
+";
+
+    fn info(duration: Option<f64>, video_streams: usize, audio_streams: usize) -> MediaInfo {
+        MediaInfo { duration, video_streams, audio_streams }
+    }
+
+    #[test]
+    fn parses_probe_output() {
+        let input = parse_media_info(INPUT);
+        assert_eq!(input.duration, Some(600.04));
+        assert_eq!((input.video_streams, input.audio_streams), (1, 2));
+
+        let unknown = parse_media_info("codec_type=video\nduration=N/A\n");
+        assert_eq!(unknown.duration, None);
+        assert_eq!(unknown.video_streams, 1);
+    }
+
+    #[test]
+    fn one_video_and_one_audio_stream_are_kept() {
+        let input = parse_media_info(INPUT);
+        assert!(compare(&input, &info(Some(600.0), 1, 1), "mkv").is_ok());
+        assert_eq!(compare(&input, &info(Some(600.0), 1, 2), "mkv").unwrap_err(), "2 audio stream(s), expected 1");
+        assert_eq!(compare(&input, &info(Some(600.0), 0, 0), "mkv").unwrap_err(), "0 video stream(s), expected 1; 0 audio stream(s), expected 1");
+        // GIF drops the audio
+        assert!(compare(&input, &info(Some(600.0), 1, 0), "gif").is_ok());
+        // Silent input, silent output
+        assert!(compare(&info(Some(10.0), 1, 0), &info(Some(10.0), 1, 0), "mp4").is_ok());
+    }
+
+    #[test]
+    fn duration_tolerance() {
+        // Short files get half a second either way
+        assert!(compare(&info(Some(10.0), 1, 0), &info(Some(10.45), 1, 0), "mp4").is_ok());
+        assert_eq!(compare(&info(Some(10.0), 1, 0), &info(Some(9.4), 1, 0), "mp4").unwrap_err(), "duration 9.40s, input is 10.00s");
+        // Long ones 1%
+        assert!(compare(&info(Some(600.0), 1, 0), &info(Some(594.5), 1, 0), "mp4").is_ok());
+        assert!(compare(&info(Some(600.0), 1, 0), &info(Some(593.0), 1, 0), "mp4").is_err());
+    }
+
+    #[test]
+    fn missing_durations() {
+        assert_eq!(compare(&info(Some(10.0), 1, 0), &info(None, 1, 0), "mp4").unwrap_err(), "output has no duration");
+        assert!(compare(&info(None, 1, 0), &info(None, 1, 0), "mp4").is_ok());
+    }
+}